@@ -1,11 +1,12 @@
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use glium::backend::Context;
 use glium;
 
 use glyph;
 
-use cu2o_term::{Cell, CharMode, Display, Term};
+use cu2o_term::{Cell, CharMode, CursorShape, Display, Term};
 use color::Manager;
 
 #[derive(Copy, Clone, Debug)]
@@ -18,6 +19,18 @@ struct ColoredVertex {
 
 implement_vertex!(ColoredVertex, xy, rgb);
 
+/// Pushes two triangles making up the quad (left, bottom) to (right, top) onto `buf`.
+#[inline]
+fn push_quad(buf: &mut Vec<ColoredVertex>, left: f32, bottom: f32, right: f32, top: f32, rgb: [f32; 3]) {
+    buf.push(ColoredVertex { xy: [left,  bottom], rgb: rgb });
+    buf.push(ColoredVertex { xy: [left,  top],    rgb: rgb });
+    buf.push(ColoredVertex { xy: [right, top],    rgb: rgb });
+
+    buf.push(ColoredVertex { xy: [right, top],    rgb: rgb });
+    buf.push(ColoredVertex { xy: [right, bottom], rgb: rgb });
+    buf.push(ColoredVertex { xy: [left,  bottom], rgb: rgb });
+}
+
 #[derive(Debug, Clone, Copy, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub enum FontStyle {
     Regular,
@@ -49,8 +62,10 @@ pub struct GlTerm<C: Manager> {
     fg_buffer: Vec<glyph::TexturedVertex>,
     /// Vertex buffer for background cells
     bg_buffer: Vec<ColoredVertex>,
-    /// Fixed size buffer for cursor, Empty = no cursor to draw
-    cu_buffer: Option<[ColoredVertex; 6]>,
+    /// Vertex buffer for cursor, empty = no cursor to draw
+    cu_buffer: Vec<ColoredVertex>,
+    /// Opacity of the cursor, filled cursors are drawn partially transparent
+    cu_alpha:  f32,
     /// Shader for rendering foreground text
     fg_shader: glium::Program,
     /// Shader for rendering background vertices
@@ -61,6 +76,18 @@ pub struct GlTerm<C: Manager> {
     colors:    C,
     /// Cellsize is the pixel-size of a cell
     cellsize:  (f32, f32),
+    /// Length of the visual bell flash, `None` disables the visual bell
+    bell_duration: Option<Duration>,
+    /// Time the visual bell was rung, `None` if no flash is in progress
+    bell_start:    Option<Instant>,
+}
+
+/// Opacity of the visual bell flash when it starts
+const BELL_ALPHA: f32 = 0.3;
+
+#[inline]
+fn millis(d: Duration) -> f32 {
+    d.as_secs() as f32 * 1000.0 + d.subsec_nanos() as f32 / 1000000.0
 }
 
 impl<C: Manager> GlTerm<C> {
@@ -137,7 +164,6 @@ impl<C: Manager> GlTerm<C> {
                 ",
             },
         ));
-        // TODO: Different cursors
         let cu_shader = try!(program!(&context,
             410 => {
                 outputs_srgb: true,
@@ -159,12 +185,14 @@ impl<C: Manager> GlTerm<C> {
                 ",
                 fragment: "   #version 410
 
+                    uniform float alpha;
+
                     in vec3 pass_rgb;
 
                     out vec4 out_color;
 
                     void main() {
-                        out_color = vec4(pass_rgb, 0.5);
+                        out_color = vec4(pass_rgb, alpha);
                     }
                 ",
             },
@@ -177,12 +205,15 @@ impl<C: Manager> GlTerm<C> {
             glyphs:    glyph_map,
             fg_buffer: Vec::new(),
             bg_buffer: Vec::new(),
-            cu_buffer: None,
+            cu_buffer: Vec::new(),
+            cu_alpha:  1.0,
             fg_shader: fg_shader,
             bg_shader: bg_shader,
             cu_shader: cu_shader,
             colors:    colors,
             cellsize:  (cellsize.0 as f32, cellsize.1 as f32),
+            bell_duration: None,
+            bell_start:    None,
         })
     }
 
@@ -230,13 +261,18 @@ impl<C: Manager> GlTerm<C> {
     }
 
     fn load_fg_vertices(&mut self, t: &Term) {
-        use cu2o_term::char_mode::BOLD;
+        use cu2o_term::char_mode::{BLINKING, BOLD};
 
         let cellsize = self.cellsize;
+        let blink    = t.blink_visible();
 
         self.fg_buffer.truncate(0);
 
         t.cells(|c| {
+            if !blink && c.attrs().contains(BLINKING) {
+                return;
+            }
+
             self.get_glyph(c.attrs().into(), c.glyph())
                 .map(|g| {
                     // No bold mapping
@@ -277,26 +313,83 @@ impl<C: Manager> GlTerm<C> {
 
         self.load_bg_vertices(t);
         self.load_fg_vertices(t);
+        self.load_cursor_vertices(t);
+    }
+
+    fn load_cursor_vertices(&mut self, t: &Term) {
+        let cellsize = self.cellsize;
 
+        self.cu_buffer.truncate(0);
+
+        if let Some(c) = t.get_cursor() {
+            // Unfocused cursors do not blink
+            if c.blink && c.focused && !t.blink_visible() {
+                return;
+            }
 
-        self.cu_buffer = t.get_cursor().map(|c| {
             // TODO: Simplify, maybe move cellsize into uniforms?
-            let left   = c.0 as f32 * self.cellsize.0;
-            let right  = left + self.cellsize.0;
-            let bottom = -((c.1 + 1) as f32) * self.cellsize.1;
-            let top    = bottom + self.cellsize.1;
+            let left   = c.col as f32 * cellsize.0;
+            let right  = left + cellsize.0;
+            let bottom = -((c.row + 1) as f32) * cellsize.1;
+            let top    = bottom + cellsize.1;
             let rgb    = [1.0, 1.0, 1.0];
+            // Thickness of lines for non-block cursors
+            let line   = (cellsize.1 / 12.0).round().max(1.0);
 
-            [
-                ColoredVertex { xy: [left,  bottom], rgb: rgb },
-                ColoredVertex { xy: [left,  top],    rgb: rgb },
-                ColoredVertex { xy: [right, top],    rgb: rgb },
+            self.cu_alpha = 1.0;
 
-                ColoredVertex { xy: [right, top],    rgb: rgb },
-                ColoredVertex { xy: [right, bottom], rgb: rgb },
-                ColoredVertex { xy: [left,  bottom], rgb: rgb },
-            ]
-        });
+            match c.shape {
+                // Unfocused terminals draw a hollow cursor
+                CursorShape::Block if c.focused => {
+                    self.cu_alpha = 0.5;
+
+                    push_quad(&mut self.cu_buffer, left, bottom, right, top, rgb);
+                },
+                CursorShape::Block => {
+                    push_quad(&mut self.cu_buffer, left,         bottom,     right, bottom + line, rgb);
+                    push_quad(&mut self.cu_buffer, left,         top - line, right, top,           rgb);
+                    push_quad(&mut self.cu_buffer, left,         bottom,     left + line, top,     rgb);
+                    push_quad(&mut self.cu_buffer, right - line, bottom,     right, top,           rgb);
+                },
+                CursorShape::Underline => push_quad(&mut self.cu_buffer, left, bottom, right, bottom + line, rgb),
+                CursorShape::Bar       => push_quad(&mut self.cu_buffer, left, bottom, left + line, top, rgb),
+            }
+        }
+    }
+
+    /// Sets the length of the visual bell flash, `None` disables it.
+    #[inline]
+    pub fn set_visual_bell(&mut self, duration: Option<Duration>) {
+        self.bell_duration = duration;
+    }
+
+    /// Starts a visual bell flash if the visual bell is enabled.
+    #[inline]
+    pub fn ring_bell(&mut self) {
+        if self.bell_duration.is_some() {
+            self.bell_start = Some(Instant::now());
+        }
+    }
+
+    /// Returns true if a visual bell flash is in progress, in which case more frames need to be
+    /// drawn to let it fade out.
+    #[inline]
+    pub fn bell_active(&self) -> bool {
+        self.bell_start.is_some()
+    }
+
+    /// Returns the current opacity of the visual bell flash, ending it once it has faded out.
+    fn bell_alpha(&mut self) -> f32 {
+        let alpha = match (self.bell_start, self.bell_duration) {
+            (Some(start), Some(duration)) => BELL_ALPHA * (1.0 - millis(start.elapsed()) / millis(duration)),
+            _                             => 0.0,
+        };
+
+        if alpha <= 0.0 {
+            self.bell_start = None;
+        }
+
+        alpha.max(0.0)
     }
 
     /// Draws the terminal onto ``target``.
@@ -341,7 +434,12 @@ impl<C: Manager> GlTerm<C> {
         // TODO: Can this be reused?
         let bg_buffer = glium::VertexBuffer::new(&self.context, &self.bg_buffer).unwrap();
         let fg_buffer = glium::VertexBuffer::new(&self.context, &self.fg_buffer).unwrap();
-        let cu_buffer = self.cu_buffer.as_ref().map(|b| glium::VertexBuffer::new(&self.context, b).unwrap());
+        let cu_buffer = glium::VertexBuffer::new(&self.context, &self.cu_buffer).unwrap();
+        let cu_uniforms = uniform! {
+            scale:  scale,
+            offset: (-1.0 + offset.0, 1.0 - offset.1),
+            alpha:  self.cu_alpha,
+        };
 
         let rgb = self.colors.fill();
         let r   = rgb[0];
@@ -353,8 +451,26 @@ impl<C: Manager> GlTerm<C> {
         target.draw(&bg_buffer, &indices, &self.bg_shader, &uniforms, &params).unwrap();
         target.draw(&fg_buffer, &indices, &self.fg_shader, &uniforms, &params).unwrap();
 
-        if let Some(b) = cu_buffer {
-            target.draw(&b, &indices, &self.cu_shader, &uniforms, &params).unwrap();
+        if !self.cu_buffer.is_empty() {
+            target.draw(&cu_buffer, &indices, &self.cu_shader, &cu_uniforms, &params).unwrap();
+        }
+
+        let bell_alpha = self.bell_alpha();
+
+        if bell_alpha > 0.0 {
+            let mut quad = Vec::with_capacity(6);
+
+            // Covers the whole framebuffer, ignoring the offset
+            push_quad(&mut quad, 0.0, -(fb_dim.1 as f32), fb_dim.0 as f32, 0.0, [1.0, 1.0, 1.0]);
+
+            let bell_buffer   = glium::VertexBuffer::new(&self.context, &quad).unwrap();
+            let bell_uniforms = uniform! {
+                scale:  scale,
+                offset: (-1.0 as f32, 1.0 as f32),
+                alpha:  bell_alpha,
+            };
+
+            target.draw(&bell_buffer, &indices, &self.cu_shader, &bell_uniforms, &params).unwrap();
         }
     }
 
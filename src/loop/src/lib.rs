@@ -4,30 +4,43 @@
 extern crate log;
 extern crate mio;
 extern crate chomp;
-extern crate glutin;
 extern crate cu2o_term;
 extern crate cu2o_system;
 
+use std::cmp;
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
 use std::io::{self, Write};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::ptr;
+use std::time::{Duration, Instant};
 
 use chomp::buffer::{FixedSizeBuffer, Source, Stream, StreamError};
 use chomp::buffer::data_source::ReadDataSource;
+use chomp::parsers::take;
 
 use mio::{EventLoop, EventLoopConfig, EventSet, Handler, PollOpt, Sender, Token, Timeout};
-use mio::unix::PipeReader;
-
-use glutin::WindowProxy;
 
 use cu2o_term::{ctrl, Term};
-use cu2o_system::{kill, ProcessId, Pty, Signal};
-
-const INPUT: Token = Token(0);
-const EXIT:  Token = Token(1);
+use cu2o_term::input::{Key, KeyState, Modifiers};
+use cu2o_term::mouse;
+use cu2o_system::{Child, ExitStatus, Pty, Signal, Signals};
+
+const INPUT:  Token = Token(0);
+const SIGNAL: Token = Token(1);
+
+/// Timers used by the event loop.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Timer {
+    /// Window wakeup
+    Frame,
+    /// Blink phase change
+    Blink,
+    /// Safety timeout for synchronized updates
+    Sync,
+}
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Message {
     /// Received resize
     Resize {
@@ -42,38 +55,114 @@ pub enum Message {
     },
     /// Exit event loop
     Exit,
-    /// Received character
-    Character(char),
+    /// Received key press, repeat or release
+    Key {
+        key:       Key,
+        modifiers: Modifiers,
+        state:     KeyState,
+    },
+    /// Mouse button, wheel or motion event
+    Mouse(mouse::Event),
+    /// Pasted text
+    Paste(String),
     /// Terminal received/lost focus
     Focus(bool),
 }
 
-struct TermHandler {
-    shell:       Pty,
-    /// Process id (and process group id) of the stream found in `shell`
-    child_pid:   ProcessId,
-    /// Pipe for self-pipe sending control data
-    ///
-    /// Never read from, but here to keep it alive until the TermHandler is destroyed.
-    _exit_pipe:  Option<PipeReader>,
-    /// Parser buffer over `shell`
+/// Wakes up the front-end when the terminal has changed or an `Event` is available.
+pub trait Notifier: Send + 'static {
+    /// Called from the event loop thread.
+    fn notify(&self);
+}
+
+impl<F> Notifier for F
+  where F: Fn() + Send + 'static {
+    fn notify(&self) {
+        self()
+    }
+}
+
+/// Events from the terminal to the window, the window event loop is woken up after each event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// The application rang the bell
+    Bell,
+    /// The child process exited, the event loop has stopped
+    ChildExited(ExitStatus),
+    /// Received a signal asking the terminal to quit, the event loop has stopped
+    Quit(Signal),
+    /// The slave side of a pseudoterminal without a child was closed, the event loop has stopped
+    Closed,
+}
+
+/// Process on the slave side of the pseudoterminal.
+enum Process {
+    /// Spawned child, reaped by the event loop
+    Child(Child),
+    /// Master side only, the slave is managed elsewhere
+    Pty(Pty),
+}
+
+impl Process {
+    fn master(&self) -> &Pty {
+        match *self {
+            Process::Child(ref c) => &c.master,
+            Process::Pty(ref p)   => p,
+        }
+    }
+
+    fn master_mut(&mut self) -> &mut Pty {
+        match *self {
+            Process::Child(ref mut c) => &mut c.master,
+            Process::Pty(ref mut p)   => p,
+        }
+    }
+}
+
+struct TermHandler<N> {
+    /// Process whose pseudoterminal is read and written
+    process:     Process,
+    /// Signals handled by the event loop, if any
+    signals:     Option<Signals>,
+    /// If reading from the pseudoterminal failed, usually because the child has exited
+    closed:      bool,
+    /// Parser buffer over the pseudoterminal
     buf:         Source<ReadDataSource<Pty>, FixedSizeBuffer<u8>>,
+    /// Size of `buf`, a full buffer cannot be filled any further
+    buffer_size: usize,
     /// Terminal data
     term:        Arc<Mutex<Term>>,
-    win:         WindowProxy,
+    /// Wakes up the window event loop
+    notifier:    N,
+    /// Channel for events to the window
+    events:      mpsc::Sender<Event>,
     /// Output buffer with data to write to the process
     out_buf:     Vec<u8>,
     /// Timeout object for the window event loop wakeup
     win_timeout: Option<Timeout>,
+    /// Timeout object forcing a redraw if a synchronized update takes too long
+    sync_timeout: Option<Timeout>,
+    /// If the terminal changed while a synchronized update was in progress
+    sync_dirty:  bool,
+    /// Timeout object for the next blink phase change
+    blink_timeout: Option<Timeout>,
+    /// Time of last user input, used to stop blinking when idle
+    last_input:  Instant,
+    /// Time without input after which blinking stops
+    blink_idle:  Duration,
+    /// Minimum time between window wakeups in milliseconds
+    frame_time:  u64,
+    /// Maximum time to hold back rendering during a synchronized update in milliseconds
+    sync_time:   u64,
 }
 
-impl TermHandler {
+impl<N: Notifier> TermHandler<N> {
     fn write_out(&mut self) -> io::Result<usize> {
         if self.out_buf.is_empty() {
             return Ok(0);
         }
 
-        self.shell.write(&self.out_buf).map(|n| {
+        self.process.master_mut().write(&self.out_buf).map(|n| {
             debug_assert!(n <= self.out_buf.len());
 
             unsafe {
@@ -103,8 +192,12 @@ impl TermHandler {
                     match s {
                         // Nothing to do
                         ctrl::Seq::SetIconName(_) => {}
-                        // TODO: Implement
-                        ctrl::Seq::Bell => {}
+                        ctrl::Seq::Bell => {
+                            // Window thread is gone if this fails
+                            if self.events.send(Event::Bell).is_ok() {
+                                self.notifier.notify();
+                            }
+                        }
                         s => {
                             // TODO: can we do something better here to determine if we actually
                             // need to update?
@@ -133,9 +226,96 @@ impl TermHandler {
         dirty
     }
 
+    /// Holds back rendering until the synchronized update is done or the timeout fires.
+    fn hold_back(&mut self, event_loop: &mut EventLoop<Self>) {
+        self.sync_dirty = true;
+
+        if self.sync_timeout.is_none() {
+            self.sync_timeout = Some(event_loop.timeout_ms(Timer::Sync, self.sync_time).unwrap());
+        }
+    }
+
+    /// Wakes up the window event loop, unless `synced` says a synchronized update is in progress.
+    fn redraw(&mut self, event_loop: &mut EventLoop<Self>, synced: bool) {
+        if synced {
+            self.hold_back(event_loop);
+        } else {
+            self.notifier.notify();
+        }
+    }
+
+    /// Starts the blink timer unless it is already running or the user is idle.
+    fn start_blink(&mut self, event_loop: &mut EventLoop<Self>) {
+        if self.blink_timeout.is_none() && self.last_input.elapsed() < self.blink_idle {
+            self.blink_timeout = Some(event_loop.timeout_ms(Timer::Blink, BLINK_TIME).unwrap());
+        }
+    }
+
+    /// Handles all pending signals.
+    fn read_signals(&mut self, event_loop: &mut EventLoop<Self>) {
+        loop {
+            let signal = match self.signals {
+                Some(ref mut s) => s.read(),
+                None            => break,
+            };
+
+            match signal {
+                Ok(Some(Signal::SigChld)) => self.check_child(event_loop),
+                Ok(Some(s @ Signal::SigTerm)) |
+                Ok(Some(s @ Signal::SigInt))  |
+                Ok(Some(s @ Signal::SigHup))  => {
+                    info!("got {:?}, exiting", s);
+
+                    // Window thread is gone if this fails
+                    if self.events.send(Event::Quit(s)).is_ok() {
+                        self.notifier.notify();
+                    }
+
+                    event_loop.shutdown();
+                },
+                // Size is determined by the window
+                Ok(Some(s))               => debug!("ignoring {:?}", s),
+                Ok(None)                  => break,
+                Err(e)                    => {
+                    error!("failed to read signals: {:?}", e);
+
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Reaps the child if it has exited, notifying the window and stopping the event loop.
+    fn check_child(&mut self, event_loop: &mut EventLoop<Self>) {
+        let child = match self.process {
+            Process::Child(ref mut c) => c,
+            Process::Pty(_)           => return,
+        };
+
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                info!("child exited, {}", status);
+
+                // Window thread is gone if this fails
+                if self.events.send(Event::ChildExited(status)).is_ok() {
+                    self.notifier.notify();
+                }
+
+                event_loop.shutdown();
+            },
+            // Some other child
+            Ok(None)         => {},
+            Err(e)           => error!("waitpid failed: {:?}", e),
+        }
+    }
+
     /// Sets the event loop to only listen for readable.
     fn set_read(&self, event_loop: &mut EventLoop<Self>) {
-        event_loop.reregister(&self.shell,
+        if self.closed {
+            return;
+        }
+
+        event_loop.reregister(self.process.master(),
                               INPUT,
                               EventSet::readable(),
                               PollOpt::level()).unwrap();
@@ -143,40 +323,110 @@ impl TermHandler {
 
     /// Sets the event loop to listen for both readable and writable events.
     fn set_write(&self, event_loop: &mut EventLoop<Self>) {
-        event_loop.reregister(&self.shell,
+        if self.closed {
+            return;
+        }
+
+        event_loop.reregister(self.process.master(),
                               INPUT,
                               EventSet::writable() | EventSet::readable(),
                               PollOpt::level()).unwrap();
     }
 }
 
+/// Default time between frames
 const FRAME_TIME: u64 = 16;
-
-impl Handler for TermHandler {
-    type Timeout = ();
+/// Default size of the read buffer, same as the chomp default
+const READ_BUFFER_SIZE: usize = 6 * 1024;
+/// Time between blink phase changes
+const BLINK_TIME: u64 = 500;
+/// Default maximum time to hold back rendering during a synchronized update
+const SYNC_TIME: u64 = 150;
+
+impl<N: Notifier> Handler for TermHandler<N> {
+    type Timeout = Timer;
     type Message = Message;
 
     fn ready(&mut self, event_loop: &mut EventLoop<Self>, token: Token, events: EventSet) {
-        if token == EXIT {
-            event_loop.shutdown();
+        if token == SIGNAL {
+            self.read_signals(event_loop);
 
             return;
         }
 
-        if events.is_readable() {
+        // A hangup of the slave is not always reported as readable, reading reports the end
+        if events.is_readable() || events.is_hup() {
+            // Only a sequence which did not fit is left if the buffer is still full after parsing
+            let full = self.buf.len() >= self.buffer_size;
+
             // TODO: Check fill rate, seems like pty buffer size is just 1K for some reason
-            self.buf.fill().unwrap();
+            let closed = match self.buf.fill() {
+                Ok(0) if full => {
+                    error!("discarding unfinished sequence longer than the read buffer ({} bytes)", self.buffer_size);
+
+                    let size = self.buffer_size;
+                    let _    = self.buf.parse(|i| take(i, size).map(|_| ()));
+
+                    false
+                },
+                // The slave has been closed, the exit of a child is reported through SIGCHLD
+                Ok(0)  => true,
+                Ok(_)  => false,
+                // Spurious wakeup
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => false,
+                Err(e) => {
+                    error!("reading from pty failed, closing: {:?}", e);
+
+                    true
+                },
+            };
 
-            let dirty = self.parse();
+            if closed {
+                info!("pty closed");
 
-            if dirty && self.win_timeout.is_none() {
+                self.closed = true;
+
+                event_loop.deregister(self.process.master()).unwrap();
+
+                if let Process::Pty(_) = self.process {
+                    // Nothing else will stop the event loop
+                    if self.events.send(Event::Closed).is_ok() {
+                        self.notifier.notify();
+                    }
+
+                    event_loop.shutdown();
+                }
+
+                return;
+            }
+
+            let dirty  = self.parse();
+            let synced = self.term.lock().expect("term::Term mutex poisoned").synchronized_update();
+
+            if dirty && synced {
+                self.hold_back(event_loop);
+            } else if dirty && self.win_timeout.is_none() {
                 info!("waking up window event loop");
 
                 // TODO: Wouldn't this imply that it sometimes renders the same frame twice in
                 // quick succession? ie. timeout fires and immediately after ready fires
-                self.win.wakeup_event_loop();
+                self.notifier.notify();
 
-                self.win_timeout = Some(event_loop.timeout_ms((), FRAME_TIME).unwrap());
+                self.win_timeout = Some(event_loop.timeout_ms(Timer::Frame, self.frame_time).unwrap());
+            }
+
+            if dirty && !synced {
+                // Update is done, or there was none
+                self.sync_dirty = false;
+
+                if let Some(t) = self.sync_timeout.take() {
+                    event_loop.clear_timeout(t);
+                }
+            }
+
+            if dirty {
+                // New content might blink
+                self.start_blink(event_loop);
             }
         }
 
@@ -194,12 +444,64 @@ impl Handler for TermHandler {
         }
     }
 
-    fn timeout(&mut self, _event_loop: &mut EventLoop<Self>, _timeout: Self::Timeout) {
-        info!("waking up window event loop");
+    fn timeout(&mut self, event_loop: &mut EventLoop<Self>, timeout: Self::Timeout) {
+        match timeout {
+            Timer::Frame => {
+                self.win_timeout = None;
+
+                if self.term.lock().expect("term::Term mutex poisoned").synchronized_update() {
+                    // Changes since the last frame are rendered when the update is done
+                    self.sync_dirty = true;
+
+                    return;
+                }
+
+                info!("waking up window event loop");
+
+                self.notifier.notify();
+            },
+            Timer::Sync => {
+                self.sync_timeout = None;
+
+                if self.sync_dirty {
+                    info!("synchronized update timed out, waking up window event loop");
+
+                    self.sync_dirty = false;
+
+                    self.notifier.notify();
+                }
+            },
+            Timer::Blink => {
+                self.blink_timeout = None;
+
+                let idle = self.last_input.elapsed() >= self.blink_idle;
+
+                let (changed, blinking, synced) = {
+                    let mut t    = self.term.lock().expect("term::Term mutex poisoned");
+                    let blinking = !idle && t.has_blinking();
+
+                    let changed = if blinking {
+                        t.toggle_blink();
+
+                        true
+                    } else {
+                        // Idle or nothing blinks anymore, stop in the visible phase, the timer is
+                        // restarted by the next keypress or new content
+                        t.reset_blink()
+                    };
 
-        self.win.wakeup_event_loop();
+                    (changed, blinking, t.synchronized_update())
+                };
 
-        self.win_timeout = None;
+                if blinking {
+                    self.blink_timeout = Some(event_loop.timeout_ms(Timer::Blink, BLINK_TIME).unwrap());
+                }
+
+                if changed {
+                    self.redraw(event_loop, synced);
+                }
+            },
+        }
     }
 
     fn notify(&mut self, event_loop: &mut EventLoop<Self>, msg: Message) {
@@ -207,26 +509,89 @@ impl Handler for TermHandler {
 
         match msg {
             Resize{ width, height, x, y } => {
-                self.term.lock().expect("term::Term mutex poisoned").resize((width as usize, height as usize));
+                self.term.lock().expect("term::Term mutex poisoned").resize((width as usize, height as usize), (x, y));
 
-                self.shell.set_window_size((width, height), (x, y)).unwrap();
+                // The kernel notifies the foreground process group with SIGWINCH
+                self.process.master_mut().set_window_size((width, height), (x, y)).unwrap();
+            },
+            Key { key, modifiers, state } => {
+                let len = self.out_buf.len();
 
-                kill(self.child_pid.process_group(), Signal::SigWinch).unwrap();
+                let (changed, synced) = {
+                    let mut t = self.term.lock().expect("term::Term mutex poisoned");
+
+                    t.encode_key(key, modifiers, state, &mut self.out_buf).unwrap();
+
+                    let echoed = t.local_echo(&self.out_buf[len..]);
+                    let reset  = state != KeyState::Release && t.reset_blink();
+
+                    (echoed || reset, t.synchronized_update())
+                };
+
+                if changed {
+                    self.redraw(event_loop, synced);
+                }
+
+                if state != KeyState::Release {
+                    self.last_input = Instant::now();
+
+                    self.start_blink(event_loop);
+                }
+
+                // Releases and modifier keys usually do not produce anything
+                if self.out_buf.len() > len {
+                    self.set_write(event_loop);
+                }
             },
-            Character(c) => {
-                write!(self.out_buf, "{}", c).unwrap();
+            Mouse(event) => {
+                let len = self.out_buf.len();
 
-                self.set_write(event_loop);
+                self.term.lock().expect("term::Term mutex poisoned").encode_mouse(event, &mut self.out_buf).unwrap();
+
+                if self.out_buf.len() > len {
+                    self.set_write(event_loop);
+                }
+            },
+            Paste(text) => {
+                let len = self.out_buf.len();
+
+                let (echoed, synced) = {
+                    let mut t = self.term.lock().expect("term::Term mutex poisoned");
+
+                    t.encode_paste(&text, &mut self.out_buf).unwrap();
+
+                    (t.local_echo(&self.out_buf[len..]), t.synchronized_update())
+                };
+
+                if echoed {
+                    self.redraw(event_loop, synced);
+                }
+
+                // Nothing is left of an empty paste or one made of control characters only
+                if self.out_buf.len() > len {
+                    self.set_write(event_loop);
+                }
             },
             Focus(got_focus) => {
-                // Check mode for focus
-                if self.term.lock().expect("term::Term mutex poisoned").send_focus_events() {
-                    if got_focus {
-                        write!(self.out_buf, "\x1B[I").unwrap();
-                    } else {
-                        write!(self.out_buf, "\x1B[O").unwrap();
-                    }
+                let len = self.out_buf.len();
+
+                let synced = {
+                    let mut t = self.term.lock().expect("term::Term mutex poisoned");
 
+                    t.set_focus(got_focus, &mut self.out_buf).unwrap();
+
+                    t.synchronized_update()
+                };
+
+                // Redraw to update the cursor
+                self.redraw(event_loop, synced);
+
+                if got_focus {
+                    // Cursor blinks again
+                    self.start_blink(event_loop);
+                }
+
+                if self.out_buf.len() > len {
                     self.set_write(event_loop);
                 }
             },
@@ -237,50 +602,189 @@ impl Handler for TermHandler {
     }
 }
 
-// TODO: Make builder
-pub fn run(mut m: Pty, child_pid: ProcessId, ctrl: Option<PipeReader>, w: WindowProxy) -> (Arc<Mutex<Term>>, Sender<Message>) {
-    let mut ev_cfg  = EventLoopConfig::new();
+/// A running terminal event loop.
+pub struct Handle {
+    /// Terminal data, locked by the event loop while it is updated
+    pub term:   Arc<Mutex<Term>>,
+    /// Sends messages to the event loop
+    pub msg:    Sender<Message>,
+    /// Events for the window
+    pub events: Receiver<Event>,
+    /// Event loop thread, returns the child, if any, once the event loop has stopped
+    pub thread: JoinHandle<Option<Child>>,
+}
+
+/// Builder for the terminal event loop.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    size:        (usize, usize),
+    buffer_size: usize,
+    frame_time:  Duration,
+    sync_time:   Duration,
+    scrollback:  usize,
+    blink_idle:  Duration,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder {
+            size:        (80, 24),
+            buffer_size: READ_BUFFER_SIZE,
+            frame_time:  Duration::from_millis(FRAME_TIME),
+            sync_time:   Duration::from_millis(SYNC_TIME),
+            scrollback:  0,
+            blink_idle:  Duration::from_secs(10),
+        }
+    }
 
-    // We do not want to block the event loop
-    m.set_noblock();
-    // Default timer tick is 100 ms which is too long
-    ev_cfg.timer_tick_ms(FRAME_TIME);
+    /// Initial size of the terminal in cells (width X height), should match the size of the
+    /// pseudoterminal.
+    pub fn with_size(mut self, width: usize, height: usize) -> Self {
+        self.size = (width, height);
 
-    let mut ev_loop = EventLoop::configured(ev_cfg).unwrap();
-    let t           = Arc::new(Mutex::new(Term::new_with_size(80, 24)));
-    let mut buf     = Source::from_read(m.clone(), FixedSizeBuffer::new());
+        self
+    }
 
-    buf.set_autofill(false);
-    ev_loop.register(&m, INPUT, EventSet::readable(), PollOpt::level()).unwrap();
+    /// Size of the buffer data from the child is read into, must not be 0.
+    ///
+    /// Sequences longer than the buffer are discarded.
+    pub fn with_buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
 
-    if let Some(c) = ctrl.as_ref() {
-        info!("Registering EXIT pipe");
+        self
+    }
 
-        ev_loop.register(c, EXIT, EventSet::readable(), PollOpt::level() | PollOpt::oneshot()).unwrap();
+    /// Minimum time between window wakeups when the terminal changes.
+    pub fn with_frame_time(mut self, frame_time: Duration) -> Self {
+        self.frame_time = frame_time;
+
+        self
     }
 
-    let mut handler = TermHandler {
-        shell:       m,
-        child_pid:   child_pid,
-        _exit_pipe:  ctrl,
-        term:        t.clone(),
-        buf:         buf,
-        win:         w,
-        win_timeout: None,
-        out_buf:     Vec::new(),
-    };
+    /// Maximum time rendering is held back while the application is in a synchronized update.
+    pub fn with_sync_timeout(mut self, sync_time: Duration) -> Self {
+        self.sync_time = sync_time;
 
-    let msg = ev_loop.channel();
+        self
+    }
 
-    thread::spawn(move || {
-        info!("Starting terminal event loop");
+    /// Number of lines scrolled off the screen to keep, 0 disables the scrollback.
+    pub fn with_scrollback(mut self, lines: usize) -> Self {
+        self.scrollback = lines;
 
-        ev_loop.run(&mut handler).unwrap();
+        self
+    }
 
-        info!("Event loop thread exiting");
+    /// Time without any user input after which the cursor and text stop blinking.
+    pub fn with_blink_idle(mut self, blink_idle: Duration) -> Self {
+        self.blink_idle = blink_idle;
+
+        self
+    }
 
-        // TODO: Action here to notify window thread that event loop thread has stopped
-    });
+    /// Runs the terminal event loop for `child` in a new thread, `notifier` is notified whenever
+    /// the window needs to redraw or handle an `Event`.
+    ///
+    /// `signals` should include `SIGCHLD`, `SIGTERM`, `SIGINT` and `SIGHUP`, the event loop stops
+    /// once `child` has exited or a signal asks it to quit.
+    ///
+    /// The child is handed back through `Handle::thread`, `Child::status` tells if it has been
+    /// reaped.
+    pub fn spawn<N>(self, child: Child, signals: Signals, notifier: N) -> io::Result<Handle>
+      where N: Notifier {
+        self.start(Process::Child(child), Some(signals), notifier)
+    }
+
+    /// Runs the terminal event loop over the master side of a pseudoterminal in a new thread,
+    /// without a child process or signal handling.
+    ///
+    /// The event loop stops with `Event::Closed` once all file descriptors of the slave side have
+    /// been closed.
+    pub fn spawn_pty<N>(self, master: Pty, notifier: N) -> io::Result<Handle>
+      where N: Notifier {
+        self.start(Process::Pty(master), None, notifier)
+    }
+
+    fn start<N>(self, mut process: Process, signals: Option<Signals>, notifier: N) -> io::Result<Handle>
+      where N: Notifier {
+        if self.buffer_size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "read buffer size must not be 0"));
+        }
+
+        let mut ev_cfg = EventLoopConfig::new();
+        let frame_time = millis(self.frame_time);
+        let mut term   = Term::new_with_size(self.size.0, self.size.1);
+
+        term.set_scrollback_limit(self.scrollback);
+
+        // We do not want to block the event loop
+        try!(process.master_mut().set_nonblocking(true));
+        // Default timer tick is 100 ms which is too long
+        ev_cfg.timer_tick_ms(cmp::max(frame_time, 1));
+
+        let mut ev_loop = try!(EventLoop::configured(ev_cfg));
+        let t           = Arc::new(Mutex::new(term));
+        let reader      = try!(process.master().try_clone());
+        let mut buf     = Source::from_read(reader, FixedSizeBuffer::with_size(self.buffer_size));
+        let (etx, erx)  = mpsc::channel();
+
+        buf.set_autofill(false);
+        try!(ev_loop.register(process.master(), INPUT, EventSet::readable(), PollOpt::level()));
+
+        if let Some(ref s) = signals {
+            try!(ev_loop.register(s, SIGNAL, EventSet::readable(), PollOpt::level()));
+        }
+
+        let mut handler = TermHandler {
+            process:     process,
+            signals:     signals,
+            closed:      false,
+            term:        t.clone(),
+            buf:         buf,
+            buffer_size: self.buffer_size,
+            notifier:    notifier,
+            events:      etx,
+            win_timeout: None,
+            sync_timeout: None,
+            sync_dirty:  false,
+            out_buf:     Vec::new(),
+            blink_timeout: None,
+            last_input:  Instant::now(),
+            blink_idle:  self.blink_idle,
+            frame_time:  frame_time,
+            sync_time:   millis(self.sync_time),
+        };
+
+        let msg = ev_loop.channel();
+
+        let thread = thread::spawn(move || {
+            info!("Starting terminal event loop");
+
+            ev_loop.run(&mut handler).unwrap();
+
+            info!("Event loop thread exiting");
+
+            match handler.process {
+                Process::Child(c) => Some(c),
+                Process::Pty(_)   => None,
+            }
+        });
+
+        Ok(Handle {
+            term:   t,
+            msg:    msg,
+            events: erx,
+            thread: thread,
+        })
+    }
+}
 
-    (t, msg)
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + d.subsec_nanos() as u64 / 1_000_000
 }
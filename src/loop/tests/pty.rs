@@ -0,0 +1,244 @@
+//! The event loop driven over a pseudoterminal pair without a window or child process.
+//!
+//! The tests act as the application on the slave side.
+
+extern crate libc;
+extern crate cu2o_loop;
+extern crate cu2o_term;
+extern crate cu2o_system;
+
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cu2o_loop::{Builder, Event, Handle, Message};
+use cu2o_term::Term;
+use cu2o_term::input::{Key, KeyState, Modifiers};
+use cu2o_system::{Pty, SetWhen};
+
+/// Milliseconds to wait for data which should arrive.
+const TIMEOUT: libc::c_int = 5000;
+
+struct Headless {
+    handle:  Handle,
+    slave:   Pty,
+    /// Receives a value each time the notifier is called
+    wakeups: Receiver<()>,
+}
+
+impl Headless {
+    fn new() -> Self {
+        Self::with_builder(Builder::new())
+    }
+
+    fn with_builder(builder: Builder) -> Self {
+        let (master, mut slave) = Pty::new_with_size((80, 24), (0, 0)).unwrap();
+        let mut t               = slave.get_attributes().unwrap();
+
+        // Pass data through unchanged
+        t.make_raw();
+
+        slave.set_attributes(&t, SetWhen::Now).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let handle   = builder.spawn_pty(master, move || { let _ = tx.send(()); }).unwrap();
+
+        Headless {
+            handle:  handle,
+            slave:   slave,
+            wakeups: rx,
+        }
+    }
+
+    /// Reads exactly `expected.len()` bytes written to the application by the terminal.
+    fn app_expect(&mut self, expected: &[u8]) {
+        let mut buf = vec![0; expected.len()];
+        let mut len = 0;
+
+        while len < buf.len() {
+            let mut fd = libc::pollfd {
+                fd:      self.slave.as_raw_fd(),
+                events:  libc::POLLIN,
+                revents: 0,
+            };
+
+            assert_eq!(unsafe { libc::poll(&mut fd, 1, TIMEOUT) }, 1, "expected {:?}, got {:?}",
+                       String::from_utf8_lossy(expected), String::from_utf8_lossy(&buf[..len]));
+
+            len += self.slave.read(&mut buf[len..]).unwrap();
+        }
+
+        assert_eq!(buf, expected);
+    }
+
+    /// Waits until `f` returns true for the terminal.
+    fn wait_for<F>(&self, f: F)
+      where F: Fn(&Term) -> bool {
+        let start = Instant::now();
+
+        while !f(&self.handle.term.lock().unwrap()) {
+            assert!(start.elapsed() < Duration::from_millis(TIMEOUT as u64), "timed out");
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Drops all pending wakeups, returns true if there were any.
+    fn woken_up(&self) -> bool {
+        let mut woken = false;
+
+        while let Ok(()) = self.wakeups.try_recv() {
+            woken = true;
+        }
+
+        woken
+    }
+
+    fn stop(self) {
+        self.handle.msg.send(Message::Exit).unwrap();
+
+        assert!(self.handle.thread.join().unwrap().is_none());
+    }
+}
+
+#[test]
+fn replies_to_the_application() {
+    let mut h = Headless::new();
+
+    h.slave.write_all(b"\x1B[c").unwrap();
+    h.app_expect(b"\x1B[?62;22c");
+
+    h.stop();
+}
+
+#[test]
+fn printed_text_wakes_up_the_front_end() {
+    let mut h = Headless::new();
+
+    // The reply is written after the terminal has been updated and the notifier called
+    h.slave.write_all(b"hello\x1B[c").unwrap();
+    h.app_expect(b"\x1B[?62;22c");
+
+    assert!(h.woken_up());
+    assert_eq!(h.handle.term.lock().unwrap().get_cursor().map(|c| c.col), Some(5));
+
+    h.stop();
+}
+
+#[test]
+fn keys_are_sent_to_the_application() {
+    let mut h = Headless::new();
+
+    for &(key, state) in &[(Key::Char('a'), KeyState::Press),
+                           (Key::Char('a'), KeyState::Release),
+                           (Key::Up, KeyState::Press)] {
+        h.handle.msg.send(Message::Key { key: key, modifiers: Modifiers::empty(), state: state }).unwrap();
+    }
+
+    h.app_expect(b"a\x1B[A");
+
+    h.stop();
+}
+
+#[test]
+fn closing_the_slave_stops_the_loop() {
+    let Headless { handle, slave, .. } = Headless::new();
+
+    drop(slave);
+
+    assert_eq!(handle.events.recv(), Ok(Event::Closed));
+    assert!(handle.thread.join().unwrap().is_none());
+}
+
+#[test]
+fn stopping_a_loop_leaves_others_running() {
+    let mut live = Headless::new();
+    let stopped  = Headless::new();
+
+    live.slave.write_all(b"\x1B[c").unwrap();
+    live.app_expect(b"\x1B[?62;22c");
+
+    // Joins the thread and drops everything the stopped loop owned
+    stopped.stop();
+
+    // Reuses the freed file descriptor numbers
+    let _reused = Pty::new().unwrap();
+
+    live.slave.write_all(b"\x1B[c").unwrap();
+    live.app_expect(b"\x1B[?62;22c");
+
+    live.stop();
+}
+
+#[test]
+fn synchronized_update_holds_back_wakeups() {
+    // Nothing but the end of the update may wake up the front-end
+    let mut h = Headless::with_builder(Builder::new()
+                                       .with_blink_idle(Duration::from_secs(0))
+                                       .with_sync_timeout(Duration::from_secs(60)));
+
+    h.slave.write_all(b"\x1B[?2026hhello\x1B[c").unwrap();
+    h.app_expect(b"\x1B[?62;22c");
+
+    assert!(!h.woken_up());
+
+    h.slave.write_all(b"\x1B[?2026l\x1B[c").unwrap();
+    h.app_expect(b"\x1B[?62;22c");
+
+    assert!(h.woken_up());
+
+    h.stop();
+}
+
+#[test]
+fn synchronized_update_holds_back_blinking() {
+    let mut h = Headless::with_builder(Builder::new().with_sync_timeout(Duration::from_secs(60)));
+
+    h.slave.write_all(b"\x1B[?2026h\x1B[5mx\x1B[c").unwrap();
+    h.app_expect(b"\x1B[?62;22c");
+
+    // Both phase changes have been handled once the blink is visible again
+    h.wait_for(|t| !t.blink_visible());
+    h.wait_for(|t| t.blink_visible());
+
+    assert!(!h.woken_up());
+
+    h.slave.write_all(b"\x1B[?2026l\x1B[c").unwrap();
+    h.app_expect(b"\x1B[?62;22c");
+
+    assert!(h.woken_up());
+
+    h.stop();
+}
+
+#[test]
+fn blinking_stops_when_idle() {
+    let mut h = Headless::with_builder(Builder::new().with_blink_idle(Duration::from_millis(1500)));
+
+    // Starts the blinking cursor
+    h.slave.write_all(b"x").unwrap();
+
+    h.wait_for(|t| !t.blink_visible());
+
+    // Idle by now, blinking stops in the visible phase
+    thread::sleep(Duration::from_secs(2));
+
+    assert!(h.handle.term.lock().unwrap().blink_visible());
+
+    h.woken_up();
+
+    thread::sleep(Duration::from_secs(1));
+
+    assert!(!h.woken_up());
+    assert!(h.handle.term.lock().unwrap().blink_visible());
+
+    // A keypress restarts it
+    h.handle.msg.send(Message::Key { key: Key::Char('a'), modifiers: Modifiers::empty(), state: KeyState::Press }).unwrap();
+    h.app_expect(b"a");
+
+    h.wait_for(|t| !t.blink_visible());
+
+    h.stop();
+}
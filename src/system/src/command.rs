@@ -0,0 +1,430 @@
+//! Spawning of processes attached to a pseudoterminal.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{CString, OsStr, OsString};
+use std::io::{Error, ErrorKind, Result};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use libc;
+
+use pty::Pty;
+use signal::{signal, unblock_all, Handler, Signal};
+use wait::{try_wait, wait, ExitStatus};
+use ::{set_cloexec, ProcessId, Session};
+
+extern {
+    static mut environ: *const *const libc::c_char;
+}
+
+/// Signals which might be ignored by the parent, dispositions other than the default are
+/// inherited through `exec`.
+const RESET_SIGNALS: &'static [Signal] = &[
+    Signal::SigChld,
+    Signal::SigHup,
+    Signal::SigInt,
+    Signal::SigPipe,
+    Signal::SigQuit,
+    Signal::SigTerm,
+    Signal::SigAlrm,
+    Signal::SigWinch,
+];
+
+/// Upper bound for file descriptors closed one by one in the child, `RLIMIT_NOFILE` can be huge.
+const MAX_CLOSE_FD: RawFd = 65536;
+
+fn cstring<S: AsRef<OsStr>>(s: S) -> Result<CString> {
+    CString::new(s.as_ref().as_bytes()).map_err(|_| Error::new(ErrorKind::InvalidInput, "nul byte in argument"))
+}
+
+/// Builder for a process running in a new session with a pseudoterminal as its controlling
+/// terminal and standard input, output and error.
+#[derive(Clone, Debug)]
+pub struct PtyCommand {
+    program: OsString,
+    args:    Vec<OsString>,
+    /// Environment changes, `None` removes the variable
+    env:     Vec<(OsString, Option<OsString>)>,
+    cwd:     Option<PathBuf>,
+    login:   bool,
+    /// Terminal size in cells and pixels
+    size:    ((u32, u32), (u32, u32)),
+    /// File descriptors kept open in the child
+    fds:     Vec<RawFd>,
+}
+
+impl PtyCommand {
+    /// Creates a command for `program`, looked up in `PATH` if it does not contain a slash.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        PtyCommand {
+            program: program.as_ref().to_owned(),
+            args:    Vec::new(),
+            env:     Vec::new(),
+            cwd:     None,
+            login:   false,
+            size:    ((0, 0), (0, 0)),
+            fds:     Vec::new(),
+        }
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+
+        self
+    }
+
+    pub fn args<S: AsRef<OsStr>>(&mut self, args: &[S]) -> &mut Self {
+        self.args.extend(args.iter().map(|a| a.as_ref().to_owned()));
+
+        self
+    }
+
+    /// Sets an environment variable, the rest of the environment is inherited.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, val: V) -> &mut Self {
+        self.env.push((key.as_ref().to_owned(), Some(val.as_ref().to_owned())));
+
+        self
+    }
+
+    /// Removes an environment variable.
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
+        self.env.push((key.as_ref().to_owned(), None));
+
+        self
+    }
+
+    /// Sets the working directory of the child.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+
+        self
+    }
+
+    /// Starts the program as a login shell, prefixing `argv[0]` with a dash (eg. `-zsh`).
+    pub fn login(&mut self, login: bool) -> &mut Self {
+        self.login = login;
+
+        self
+    }
+
+    /// Sets the initial size of the pseudoterminal (width X height).
+    pub fn size(&mut self, term: (u32, u32), pixels: (u32, u32)) -> &mut Self {
+        self.size = (term, pixels);
+
+        self
+    }
+
+    /// Passes `fd` to the child under the same number.
+    ///
+    /// All file descriptors except for standard input, output and error are closed in the child
+    /// unless passed explicitly. Standard input, output and error are the pseudoterminal and
+    /// cannot be passed, `spawn` fails with `InvalidInput` if `fd` is one of them.
+    pub fn inherit_fd(&mut self, fd: RawFd) -> &mut Self {
+        self.fds.push(fd);
+
+        self
+    }
+
+    fn argv0(&self) -> OsString {
+        if !self.login {
+            return self.program.clone();
+        }
+
+        let name = Path::new(&self.program).file_name().unwrap_or(self.program.as_os_str());
+        let mut argv0 = OsString::from("-");
+
+        argv0.push(name);
+
+        argv0
+    }
+
+    fn envp(&self) -> Result<Vec<CString>> {
+        let mut vars: HashMap<OsString, OsString> = env::vars_os().collect();
+
+        for &(ref k, ref v) in &self.env {
+            match *v {
+                Some(ref v) => { vars.insert(k.clone(), v.clone()); },
+                None        => { vars.remove(k); },
+            }
+        }
+
+        vars.into_iter().map(|(k, v)| {
+            let mut var = k;
+
+            var.push("=");
+            var.push(v);
+
+            cstring(var)
+        }).collect()
+    }
+
+    /// Opens a new pseudoterminal and starts the program in it.
+    pub fn spawn(&self) -> Result<Child> {
+        if self.fds.iter().any(|&fd| fd <= libc::STDERR_FILENO) {
+            return Err(Error::new(ErrorKind::InvalidInput, "standard input, output and error cannot be inherited"));
+        }
+
+        let (master, slave) = try!(Pty::new_with_size(self.size.0, self.size.1));
+
+        // Everything is allocated before forking, only async-signal-safe calls are allowed in the
+        // child of a multi-threaded process
+        let program  = try!(cstring(&self.program));
+        let mut args = vec![try!(cstring(self.argv0()))];
+
+        for a in &self.args {
+            args.push(try!(cstring(a)));
+        }
+
+        let envs     = try!(self.envp());
+        let cwd      = match self.cwd {
+            Some(ref d) => Some(try!(cstring(d))),
+            None        => None,
+        };
+        let mut argv = args.iter().map(|a| a.as_ptr()).collect::<Vec<_>>();
+        let mut envp = envs.iter().map(|e| e.as_ptr()).collect::<Vec<_>>();
+
+        argv.push(ptr::null());
+        envp.push(ptr::null());
+
+        // Upper bound for file descriptors to close in the child
+        let max_fd = match unsafe { libc::sysconf(libc::_SC_OPEN_MAX) } {
+            n if n > 0 => cmp::min(n, MAX_CLOSE_FD as libc::c_long) as RawFd,
+            _          => 1024,
+        };
+
+        // Reports errors from the child before exec, closed on a successful exec
+        let mut fds = [0; 2];
+
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(Error::last_os_error());
+        }
+
+        if let Err(e) = set_cloexec(fds[0]).and_then(|_| set_cloexec(fds[1])) {
+            unsafe {
+                libc::close(fds[0]);
+                libc::close(fds[1]);
+            }
+
+            return Err(e);
+        }
+
+        // Sorted for closing everything in between
+        let mut keep_fds = self.fds.clone();
+
+        keep_fds.push(fds[1]);
+        keep_fds.sort();
+        keep_fds.dedup();
+
+        match unsafe { libc::fork() } {
+            -1  => {
+                let e = Error::last_os_error();
+
+                unsafe {
+                    libc::close(fds[0]);
+                    libc::close(fds[1]);
+                }
+
+                Err(e)
+            },
+            0   => {
+                unsafe { libc::close(fds[0]) };
+
+                let keep = Fds { keep: &keep_fds, error: fds[1], max: max_fd };
+                let e    = unsafe { exec_child(master, slave, &program, &argv, &envp, cwd.as_ref(), keep) };
+                let errno: [u8; 4] = unsafe { mem::transmute(e.raw_os_error().unwrap_or(0)) };
+
+                unsafe {
+                    libc::write(fds[1], errno.as_ptr() as *const libc::c_void, errno.len());
+                    libc::_exit(127);
+                }
+            },
+            pid => {
+                drop(slave);
+
+                unsafe { libc::close(fds[1]) };
+
+                let mut errno = [0u8; 4];
+                let mut read  = 0;
+
+                while read < errno.len() {
+                    match unsafe { libc::read(fds[0], errno[read..].as_mut_ptr() as *mut libc::c_void, errno.len() - read) } {
+                        -1 if Error::last_os_error().kind() == ErrorKind::Interrupted => {},
+                        -1 | 0 => break,
+                        n      => read += n as usize,
+                    }
+                }
+
+                unsafe { libc::close(fds[0]) };
+
+                let pid = ProcessId(pid);
+
+                if read == errno.len() {
+                    // Reap the failed child
+                    let _ = wait(pid);
+
+                    return Err(Error::from_raw_os_error(unsafe { mem::transmute(errno) }));
+                }
+
+                Ok(Child {
+                    master: master,
+                    pid:    pid,
+                    status: None,
+                })
+            },
+        }
+    }
+}
+
+/// File descriptors to keep open in the child.
+struct Fds<'a> {
+    /// Sorted, explicitly passed to the child and the error pipe
+    keep:  &'a [RawFd],
+    /// Error reporting pipe, closed on exec
+    error: RawFd,
+    /// Upper bound for open file descriptors if they have to be closed one by one
+    max:   RawFd,
+}
+
+/// Closes the file descriptors from `first` to `last` inclusive, uses `close_range` if
+/// available, otherwise file descriptors from `max` on are left open.
+///
+/// Async-signal-safe.
+unsafe fn close_fds(first: RawFd, last: RawFd, max: RawFd) {
+    if first > last {
+        return;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if libc::syscall(libc::SYS_close_range, first as libc::c_uint, last as libc::c_uint, 0 as libc::c_uint) == 0 {
+            return;
+        }
+    }
+
+    for fd in first..cmp::min(last, max - 1) + 1 {
+        libc::close(fd);
+    }
+}
+
+/// Sets up the child process and runs the program, only returns on error.
+///
+/// Only async-signal-safe functions may be used.
+unsafe fn exec_child(master: Pty, slave: Pty, program: &CString, argv: &[*const libc::c_char], envp: &[*const libc::c_char], cwd: Option<&CString>, fds: Fds) -> Error {
+    macro_rules! check {
+        ( $e:expr ) => {
+            if $e == -1 {
+                return Error::last_os_error();
+            }
+        }
+    }
+
+    // The master is only used by the parent
+    drop(master);
+
+    // New session without a controlling terminal, the slave then becomes the controlling terminal
+    check!(libc::setsid());
+    check!(libc::ioctl(slave.as_raw_fd(), libc::TIOCSCTTY as _, 0));
+
+    for &fd in &[libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if let Err(e) = slave.override_fd(fd) {
+            return e;
+        }
+    }
+
+    if slave.as_raw_fd() > libc::STDERR_FILENO {
+        drop(slave);
+    } else {
+        // One of the standard fds now, keep it open
+        mem::forget(slave);
+    }
+
+    // Do not leak anything else into the child
+    let mut next = libc::STDERR_FILENO + 1;
+
+    for &fd in fds.keep {
+        close_fds(next, fd - 1, fds.max);
+
+        if fd != fds.error {
+            check!(libc::fcntl(fd, libc::F_SETFD, libc::fcntl(fd, libc::F_GETFD) & !libc::FD_CLOEXEC));
+        }
+
+        next = fd + 1;
+    }
+
+    close_fds(next, RawFd::max_value(), fds.max);
+
+    for &s in RESET_SIGNALS {
+        if let Err(e) = signal(s, Handler::Default) {
+            return e;
+        }
+    }
+
+    if let Err(e) = unblock_all() {
+        return e;
+    }
+
+    if let Some(dir) = cwd {
+        check!(libc::chdir(dir.as_ptr()));
+    }
+
+    environ = envp.as_ptr();
+
+    libc::execvp(program.as_ptr(), argv.as_ptr());
+
+    // A return from execvp means an error occurred
+    Error::last_os_error()
+}
+
+/// A process running in a pseudoterminal.
+#[derive(Debug)]
+pub struct Child {
+    /// Master side of the pseudoterminal
+    pub master: Pty,
+    pid:        ProcessId,
+    /// Exit status once reaped
+    status:     Option<ExitStatus>,
+}
+
+impl Child {
+    pub fn pid(&self) -> ProcessId {
+        self.pid
+    }
+
+    /// Session led by the child, `master` is its controlling terminal.
+    pub fn session(&self) -> Session {
+        Session(self.pid.0)
+    }
+
+    /// Exit status if the child has been reaped.
+    pub fn status(&self) -> Option<ExitStatus> {
+        self.status
+    }
+
+    /// Reaps the child if it has exited, returns `Ok(None)` if it is still running.
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = try!(try_wait(self.pid));
+        }
+
+        Ok(self.status)
+    }
+
+    /// Blocks until the child has exited.
+    pub fn wait(&mut self) -> Result<ExitStatus> {
+        match self.status {
+            Some(s) => Ok(s),
+            None    => {
+                let s = try!(wait(self.pid));
+
+                self.status = Some(s);
+
+                Ok(s)
+            },
+        }
+    }
+}
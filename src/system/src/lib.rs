@@ -6,19 +6,28 @@ use std::fmt;
 use std::ptr;
 use std::ffi::CString;
 use std::io::{Error, Result};
+use std::os::unix::io::RawFd;
 
 #[macro_use]
 mod atomic_ptr;
-mod signal;
+mod command;
+pub mod signal;
 mod pty;
+#[cfg(target_os = "linux")]
+mod procfs;
 mod selfpipe;
+mod termios;
+mod wait;
 
 pub use libc::{STDIN_FILENO, STDOUT_FILENO, STDERR_FILENO};
 
 pub use atomic_ptr::AtomicPtr;
-pub use signal::{kill, signal, Signal, KillTarget};
+pub use command::{Child, PtyCommand};
+pub use signal::{kill, signal, Signal, Signals, KillTarget};
 pub use signal::Handler as SignalHandler;
 pub use pty::Pty;
+pub use termios::{RawMode, SetWhen, Termios};
+pub use wait::{try_wait, wait, ExitStatus};
 
 // TODO: Should probably be unsigned
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -31,9 +40,20 @@ impl fmt::Display for ProcessId {
 }
 
 impl ProcessId {
-    // TODO: This assumes that the process is its own process group
-    pub fn process_group(&self) -> ProcessGroup {
-        ProcessGroup(self.0)
+    /// Process group the process belongs to (`getpgid`).
+    pub fn process_group(&self) -> Result<ProcessGroup> {
+        match unsafe { libc::getpgid(self.0) } {
+            -1   => Err(Error::last_os_error()),
+            pgid => Ok(ProcessGroup(pgid)),
+        }
+    }
+
+    /// Session the process belongs to (`getsid`).
+    pub fn session(&self) -> Result<Session> {
+        match unsafe { libc::getsid(self.0) } {
+            -1  => Err(Error::last_os_error()),
+            sid => Ok(Session(sid)),
+        }
     }
 }
 
@@ -41,12 +61,51 @@ impl ProcessId {
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ProcessGroup(libc::pid_t);
 
+impl ProcessGroup {
+    /// Process id of the group leader, the leader might have exited while the group still exists.
+    pub fn leader(&self) -> ProcessId {
+        ProcessId(self.0)
+    }
+}
+
 impl fmt::Display for ProcessGroup {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.0.fmt(f)
     }
 }
 
+/// Session id, the process id of the session leader.
+// TODO: Should probably be unsigned
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Session(libc::pid_t);
+
+impl Session {
+    /// Process id of the session leader, the leader might have exited while the session still
+    /// exists.
+    pub fn leader(&self) -> ProcessId {
+        ProcessId(self.0)
+    }
+
+    /// Process group of the session leader, the leader is also the leader of its process group.
+    pub fn process_group(&self) -> ProcessGroup {
+        ProcessGroup(self.0)
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Sets the close-on-exec flag on `fd`, all file descriptors created by this crate have it set.
+pub fn set_cloexec(fd: RawFd) -> Result<()> {
+    match unsafe { libc::fcntl(fd, libc::F_SETFD, libc::fcntl(fd, libc::F_GETFD) | libc::FD_CLOEXEC) } {
+        -1 => Err(Error::last_os_error()),
+        _  => Ok(()),
+    }
+}
+
 /// Forks the process, returning Ok(Some(ProcessId)) to the parent and Ok(None) to the child.
 pub fn fork() -> Result<Option<ProcessId>> {
     match unsafe { libc::fork() } {
@@ -67,10 +126,10 @@ pub fn create_process_group() -> Result<()> {
 
 /// Creates a new session if the current process is not a process group leader. The current process
 /// becomes the process group leader of the new session.
-pub fn create_session() -> Result<ProcessGroup> {
+pub fn create_session() -> Result<Session> {
     match unsafe { libc::setsid() } {
         -1 => Err(Error::last_os_error()),
-        n  => Ok(ProcessGroup(n)),
+        n  => Ok(Session(n)),
     }
 }
 
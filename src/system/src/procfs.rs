@@ -0,0 +1,94 @@
+//! Process information from `/proc`, only available on Linux.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{Read, Result};
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+
+use ::ProcessId;
+
+impl ProcessId {
+    fn proc_path(&self, file: &str) -> PathBuf {
+        PathBuf::from(format!("/proc/{}/{}", self.0, file))
+    }
+
+    fn read_proc(&self, file: &str) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+
+        try!(File::open(self.proc_path(file)).and_then(|mut f| f.read_to_end(&mut buf)));
+
+        Ok(buf)
+    }
+
+    /// Command name of the process, usually the executable name truncated to 15 bytes.
+    pub fn name(&self) -> Result<String> {
+        let comm = try!(self.read_proc("comm"));
+
+        Ok(String::from_utf8_lossy(&comm).trim_right_matches('\n').to_owned())
+    }
+
+    /// Path to the executable of the process, might not be readable for processes of other
+    /// users.
+    pub fn exe(&self) -> Result<PathBuf> {
+        fs::read_link(self.proc_path("exe"))
+    }
+
+    /// Arguments of the process including `argv[0]`, empty for zombies and kernel threads.
+    pub fn args(&self) -> Result<Vec<OsString>> {
+        let mut cmdline = try!(self.read_proc("cmdline"));
+
+        // Arguments are nul-terminated
+        if cmdline.last() == Some(&0) {
+            cmdline.pop();
+        }
+
+        if cmdline.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(cmdline.split(|&b| b == 0).map(|a| OsString::from_vec(a.to_owned())).collect())
+    }
+
+    /// Current working directory of the process.
+    pub fn cwd(&self) -> Result<PathBuf> {
+        fs::read_link(self.proc_path("cwd"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use libc;
+
+    use ::ProcessId;
+
+    fn current() -> ProcessId {
+        ProcessId(unsafe { libc::getpid() })
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(current().args().unwrap(), env::args_os().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cwd() {
+        assert_eq!(current().cwd().unwrap(), env::current_dir().unwrap());
+    }
+
+    #[test]
+    fn exe() {
+        assert_eq!(current().exe().unwrap(), env::current_exe().unwrap());
+    }
+
+    #[test]
+    fn name() {
+        let exe  = env::current_exe().unwrap();
+        let name = exe.file_name().unwrap().to_string_lossy();
+
+        // Truncated to 15 bytes, test executables are usually longer
+        assert!(name.starts_with(&current().name().unwrap()));
+    }
+}
@@ -5,7 +5,7 @@ use std::ptr;
 use libc;
 use errno;
 
-const EAGAIN: libc::c_int = libc::EAGAIN as libc::c_int;
+use {set_cloexec, ProcessGroup, Session};
 
 // OS X:
 #[cfg(any(target_os = "macos", target_os = "ios"))]
@@ -13,8 +13,8 @@ const TIOCSWINSZ: libc::c_ulong = 0x80087467;
 #[cfg(not(any(target_os ="macos", target_os = "ios")))]
 use libc::TIOCSWINSZ;
 
-/// Pseudoterminal
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+/// Pseudoterminal, the file descriptor is closed on drop.
+#[derive(Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Pty {
    fd: RawFd
 }
@@ -39,13 +39,30 @@ impl Pty {
             ws_ypixel: pixels.1 as libc::c_ushort,
         };
 
-        match unsafe { libc::openpty(&mut m, &mut s, ptr::null_mut(), ptr::null_mut(), &mut ws) } {
+        if unsafe { libc::openpty(&mut m, &mut s, ptr::null_mut(), ptr::null_mut(), &mut ws) } == -1 {
+            return Err(Error::last_os_error());
+        }
+
+        let (m, s) = (Pty{fd: m}, Pty{fd: s});
+
+        // Not to be inherited by other processes, PtyCommand passes the slave as stdio
+        try!(set_cloexec(m.fd));
+        try!(set_cloexec(s.fd));
+
+        Ok((m, s))
+    }
+
+    /// Duplicates the file descriptor, the new descriptor is close-on-exec and shares the file
+    /// status flags like non-blocking mode.
+    pub fn try_clone(&self) -> Result<Self> {
+        match unsafe { libc::fcntl(self.fd, libc::F_DUPFD_CLOEXEC, 0) } {
             -1 => Err(Error::last_os_error()),
-            _  => Ok((Pty{fd: m}, Pty{fd: s}))
+            fd => Ok(Pty{fd: fd}),
         }
     }
 
-    /// Updates the window-size of the pseudoterminal (width X height) using `ioctl`.
+    /// Updates the window-size of the pseudoterminal (width X height) using `ioctl`, the kernel
+    /// sends `SIGWINCH` to the foreground process group if the size changed.
     pub fn set_window_size(&mut self, term: (u32, u32), pixels: (u32, u32)) -> Result<()> {
         let ws = libc::winsize {
             ws_row:    term.1 as libc::c_ushort,
@@ -60,6 +77,22 @@ impl Pty {
         }
     }
 
+    /// Process group of the foreground job of the terminal (`tcgetpgrp`).
+    pub fn foreground_process_group(&self) -> Result<ProcessGroup> {
+        match unsafe { libc::tcgetpgrp(self.fd) } {
+            -1  => Err(Error::last_os_error()),
+            pid => Ok(ProcessGroup(pid)),
+        }
+    }
+
+    /// Session which has the pseudoterminal as its controlling terminal (`tcgetsid`).
+    pub fn session(&self) -> Result<Session> {
+        match unsafe { libc::tcgetsid(self.fd) } {
+            -1  => Err(Error::last_os_error()),
+            sid => Ok(Session(sid)),
+        }
+    }
+
     /// Overrides the specified file-descriptor given with the
     /// internal file-descriptor.
     pub fn override_fd(&self, fd: RawFd) -> Result<()> {
@@ -69,11 +102,22 @@ impl Pty {
         }
     }
 
-    // TODO: Make it possible to turn blocking back on
-    pub fn set_noblock(&mut self) {
-        match unsafe { libc::fcntl(self.fd, libc::F_SETFL, libc::fcntl(self.fd, libc::F_GETFL) | libc::O_NONBLOCK) } {
-            -1 => panic!(Error::last_os_error()),
-            _  => ()
+    /// Sets non-blocking mode, reads and writes which would block return
+    /// `ErrorKind::WouldBlock` instead.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        let flags = match unsafe { libc::fcntl(self.fd, libc::F_GETFL) } {
+            -1    => return Err(Error::last_os_error()),
+            flags => flags,
+        };
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+
+        match unsafe { libc::fcntl(self.fd, libc::F_SETFL, flags) } {
+            -1 => Err(Error::last_os_error()),
+            _  => Ok(()),
         }
     }
 }
@@ -86,11 +130,14 @@ impl FromRawFd for Pty {
 }
 
 impl Read for Pty {
+    /// Reads from the pseudoterminal, on the master side the end of the stream is reached once
+    /// all slave file descriptors have been closed.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         match unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } {
             -1 => match errno::errno().0 {
-                EAGAIN => Ok(0),
-                _      => Err(Error::last_os_error())
+                // Linux reports a hangup of the slave as EIO
+                libc::EIO => Ok(0),
+                _         => Err(Error::last_os_error()),
             },
             r  => Ok(r as usize),
         }
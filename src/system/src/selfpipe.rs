@@ -1,9 +1,100 @@
-/*
-use libc::pipe;
+//! Self-pipe which turns signals into readable bytes, used where signalfd is not available.
 
+use std::io::{ErrorKind, Read, Result};
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicIsize, Ordering, ATOMIC_ISIZE_INIT};
+
+use errno;
+use libc;
+use mio::{Evented, EventSet, PollOpt, Selector, Token};
+use mio::unix::{pipe, PipeReader, PipeWriter};
+
+use set_cloexec;
+use signal::{signal, Handler, Signal};
+
+/// Write end of the pipe, 0 when not installed (stdin is never the write end).
+static PIPE: AtomicIsize = ATOMIC_ISIZE_INIT;
+
+extern fn on_signal(s: Signal) {
+    let fd = PIPE.load(Ordering::SeqCst);
+
+    if fd == 0 {
+        return;
+    }
+
+    // Only async-signal-safe functions in here, errno has to be preserved for the interrupted code
+    let e    = errno::errno();
+    let byte = s as u8;
+
+    // If the pipe is full the reader is already notified
+    unsafe { libc::write(fd as libc::c_int, &byte as *const u8 as *const libc::c_void, 1) };
+
+    errno::set_errno(e);
+}
+
+/// Installs signal handlers writing the signal number to a non-blocking pipe.
+///
+/// Only one `SelfPipe` can be active at a time, creating a new one replaces the previous pipe.
 pub struct SelfPipe {
-    fn
+    reader: PipeReader,
+    writer: PipeWriter,
 }
 
-pub fn new()
-*/
+impl SelfPipe {
+    pub fn new(signals: &[Signal]) -> Result<Self> {
+        let (reader, writer) = try!(pipe());
+
+        try!(set_cloexec(reader.as_raw_fd()));
+        try!(set_cloexec(writer.as_raw_fd()));
+
+        PIPE.store(writer.as_raw_fd() as isize, Ordering::SeqCst);
+
+        for &s in signals {
+            try!(signal(s, Handler::Handler(on_signal)));
+        }
+
+        Ok(SelfPipe {
+            reader: reader,
+            writer: writer,
+        })
+    }
+
+    /// Reads the next signal, returns `Ok(None)` if no signal is pending.
+    pub fn read(&mut self) -> Result<Option<Signal>> {
+        let mut buf = [0];
+
+        loop {
+            match self.reader.read(&mut buf) {
+                Ok(0)  => return Ok(None),
+                Ok(_)  => return Ok(Signal::from_raw(buf[0] as libc::c_int)),
+                Err(e) => match e.kind() {
+                    ErrorKind::Interrupted => {},
+                    ErrorKind::WouldBlock  => return Ok(None),
+                    _                      => return Err(e),
+                },
+            }
+        }
+    }
+}
+
+impl Drop for SelfPipe {
+    fn drop(&mut self) {
+        // Stop the handlers from writing to the pipe before it is closed, unless it already has
+        // been replaced by another pipe
+        PIPE.compare_and_swap(self.writer.as_raw_fd() as isize, 0, Ordering::SeqCst);
+    }
+}
+
+impl Evented for SelfPipe {
+    fn register(&self, selector: &mut Selector, token: Token, interest: EventSet, opts: PollOpt) -> Result<()> {
+        self.reader.register(selector, token, interest, opts)
+    }
+
+    fn reregister(&self, selector: &mut Selector, token: Token, interest: EventSet, opts: PollOpt) -> Result<()> {
+        self.reader.reregister(selector, token, interest, opts)
+    }
+
+    fn deregister(&self, selector: &mut Selector) -> Result<()> {
+        self.reader.deregister(selector)
+    }
+}
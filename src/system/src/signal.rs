@@ -1,8 +1,17 @@
-use std::io::{Error, Result};
+//! Signal dispositions and delivery of signals as events.
+
+use std::io::{Error, ErrorKind, Result};
+use std::mem;
 use std::mem::transmute;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::RawFd;
 
 use libc;
+use mio::{Evented, EventSet, PollOpt, Selector, Token};
+#[cfg(target_os = "linux")]
+use mio::unix::EventedFd;
 
+use selfpipe::SelfPipe;
 use ::{ProcessGroup, ProcessId};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -39,6 +48,44 @@ pub enum Signal {
     SigXfsz    = libc::SIGXFSZ,
 }
 
+impl Signal {
+    /// Converts a signal number to a `Signal`, `None` if it is not one of the listed signals.
+    pub fn from_raw(s: libc::c_int) -> Option<Signal> {
+        match s {
+            libc::SIGABRT   => Some(Signal::SigAbrt),
+            libc::SIGALRM   => Some(Signal::SigAlrm),
+            libc::SIGBUS    => Some(Signal::SigBus),
+            libc::SIGCHLD   => Some(Signal::SigChld),
+            libc::SIGCONT   => Some(Signal::SigCont),
+            libc::SIGFPE    => Some(Signal::SigFpe),
+            libc::SIGHUP    => Some(Signal::SigHup),
+            libc::SIGILL    => Some(Signal::SigIll),
+            libc::SIGINT    => Some(Signal::SigInt),
+            libc::SIGIO     => Some(Signal::SigIo),
+            libc::SIGKILL   => Some(Signal::SigKill),
+            libc::SIGPIPE   => Some(Signal::SigPipe),
+            libc::SIGPROF   => Some(Signal::SigProf),
+            libc::SIGQUIT   => Some(Signal::SigQuit),
+            libc::SIGSEGV   => Some(Signal::SigSegv),
+            libc::SIGSTOP   => Some(Signal::SigStop),
+            libc::SIGSYS    => Some(Signal::SigSys),
+            libc::SIGTERM   => Some(Signal::SigTerm),
+            libc::SIGTRAP   => Some(Signal::SigTrap),
+            libc::SIGTSTP   => Some(Signal::SigStp),
+            libc::SIGTTIN   => Some(Signal::SigTtin),
+            libc::SIGTTOU   => Some(Signal::SigTtou),
+            libc::SIGURG    => Some(Signal::SigUrg),
+            libc::SIGUSR1   => Some(Signal::SigUsr1),
+            libc::SIGUSR2   => Some(Signal::SigUsr2),
+            libc::SIGVTALRM => Some(Signal::SigVtalrm),
+            libc::SIGWINCH  => Some(Signal::SigWinch),
+            libc::SIGXCPU   => Some(Signal::SigXcpu),
+            libc::SIGXFSZ   => Some(Signal::SigXfsz),
+            _               => None,
+        }
+    }
+}
+
 impl From<Signal> for libc::c_int {
     fn from(s: Signal) -> libc::c_int {
         s as libc::c_int
@@ -125,3 +172,155 @@ pub fn signal(s: Signal, h: Handler) -> Result<Handler> {
         _   => Ok(old.into()),
     }
 }
+
+/// Creates a signal set containing `signals`.
+fn sigset(signals: &[Signal]) -> libc::sigset_t {
+    unsafe {
+        let mut set = mem::zeroed();
+
+        libc::sigemptyset(&mut set);
+
+        for &s in signals {
+            libc::sigaddset(&mut set, s.into());
+        }
+
+        set
+    }
+}
+
+/// Unblocks all signals of the calling thread.
+///
+/// The signal mask is inherited through `fork` and `exec`, child processes need to call this if
+/// the parent created `Signals`.
+pub fn unblock_all() -> Result<()> {
+    let set = sigset(&[]);
+
+    match unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &set, ::std::ptr::null_mut()) } {
+        0 => Ok(()),
+        e => Err(Error::from_raw_os_error(e)),
+    }
+}
+
+enum Source {
+    /// Signals are blocked and read from a signalfd
+    #[cfg(target_os = "linux")]
+    SignalFd(RawFd),
+    /// Signal handlers write to a pipe
+    SelfPipe(SelfPipe),
+}
+
+/// Signals delivered as readable events, register it in an event loop and use `read` to receive
+/// them.
+///
+/// On Linux the signals are blocked and read through a signalfd, this requires `Signals` to be
+/// created before any other thread is started as the signal mask is inherited by new threads.
+/// Elsewhere, or if signalfd is unavailable, signal handlers writing to a self-pipe are used.
+///
+/// Signals of the same kind arriving before they are read might be coalesced.
+pub struct Signals {
+    source: Source,
+}
+
+impl Signals {
+    pub fn new(signals: &[Signal]) -> Result<Self> {
+        let source = match Self::signalfd(signals) {
+            Ok(fd) => fd,
+            // Not available, fall back to the self-pipe
+            Err(_) => Source::SelfPipe(try!(SelfPipe::new(signals))),
+        };
+
+        Ok(Signals {
+            source: source,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn signalfd(signals: &[Signal]) -> Result<Source> {
+        let set = sigset(signals);
+
+        let fd = match unsafe { libc::signalfd(-1, &set, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC) } {
+            -1 => return Err(Error::last_os_error()),
+            fd => fd,
+        };
+
+        // Signals have to be blocked to not be delivered the usual way
+        match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ::std::ptr::null_mut()) } {
+            0 => Ok(Source::SignalFd(fd)),
+            e => {
+                unsafe { libc::close(fd) };
+
+                Err(Error::from_raw_os_error(e))
+            },
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn signalfd(_: &[Signal]) -> Result<Source> {
+        Err(Error::new(ErrorKind::Other, "signalfd is only available on Linux"))
+    }
+
+    /// Reads the next pending signal, returns `Ok(None)` if no signal is pending.
+    pub fn read(&mut self) -> Result<Option<Signal>> {
+        match self.source {
+            #[cfg(target_os = "linux")]
+            Source::SignalFd(fd) => {
+                let mut info: libc::signalfd_siginfo = unsafe { mem::zeroed() };
+                let size = mem::size_of::<libc::signalfd_siginfo>();
+
+                loop {
+                    match unsafe { libc::read(fd, &mut info as *mut _ as *mut libc::c_void, size) } {
+                        -1 => {
+                            let e = Error::last_os_error();
+
+                            match e.kind() {
+                                ErrorKind::Interrupted => {},
+                                ErrorKind::WouldBlock  => return Ok(None),
+                                _                      => return Err(e),
+                            }
+                        },
+                        n if n as usize == size => return Ok(Signal::from_raw(info.ssi_signo as libc::c_int)),
+                        _  => return Err(Error::new(ErrorKind::InvalidData, "short read from signalfd")),
+                    }
+                }
+            },
+            Source::SelfPipe(ref mut p) => p.read(),
+        }
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        match self.source {
+            #[cfg(target_os = "linux")]
+            Source::SignalFd(fd) => unsafe { libc::close(fd); },
+            // Handlers are left installed
+            Source::SelfPipe(_)  => {},
+        }
+    }
+}
+
+impl Evented for Signals {
+    fn register(&self, selector: &mut Selector, token: Token, interest: EventSet, opts: PollOpt) -> Result<()> {
+        match self.source {
+            #[cfg(target_os = "linux")]
+            Source::SignalFd(ref fd) => EventedFd(fd).register(selector, token, interest, opts),
+            Source::SelfPipe(ref p)  => p.register(selector, token, interest, opts),
+        }
+    }
+
+    fn reregister(&self, selector: &mut Selector, token: Token, interest: EventSet, opts: PollOpt) -> Result<()> {
+        match self.source {
+            #[cfg(target_os = "linux")]
+            Source::SignalFd(ref fd) => EventedFd(fd).reregister(selector, token, interest, opts),
+            Source::SelfPipe(ref p)  => p.reregister(selector, token, interest, opts),
+        }
+    }
+
+    fn deregister(&self, selector: &mut Selector) -> Result<()> {
+        match self.source {
+            #[cfg(target_os = "linux")]
+            Source::SignalFd(ref fd) => EventedFd(fd).deregister(selector),
+            Source::SelfPipe(ref p)  => p.deregister(selector),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Terminal attributes of a pseudoterminal.
+
+use std::fmt;
+use std::io::{Error, Result};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
+
+use libc;
+
+use pty::Pty;
+
+/// When changed attributes take effect.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SetWhen {
+    /// Immediately
+    Now,
+    /// After all output has been transmitted
+    Drain,
+    /// After all output has been transmitted, pending input is discarded
+    Flush,
+}
+
+impl From<SetWhen> for libc::c_int {
+    fn from(w: SetWhen) -> libc::c_int {
+        match w {
+            SetWhen::Now   => libc::TCSANOW,
+            SetWhen::Drain => libc::TCSADRAIN,
+            SetWhen::Flush => libc::TCSAFLUSH,
+        }
+    }
+}
+
+/// Terminal attributes (`struct termios`).
+#[derive(Clone, Copy)]
+pub struct Termios(libc::termios);
+
+impl Termios {
+    /// If typed characters are echoed back.
+    pub fn echo(&self) -> bool {
+        self.0.c_lflag & libc::ECHO == libc::ECHO
+    }
+
+    /// If input is line buffered (canonical mode).
+    pub fn canonical(&self) -> bool {
+        self.0.c_lflag & libc::ICANON == libc::ICANON
+    }
+
+    pub fn set_echo(&mut self, echo: bool) {
+        if echo {
+            self.0.c_lflag |= libc::ECHO;
+        } else {
+            self.0.c_lflag &= !libc::ECHO;
+        }
+    }
+
+    pub fn set_canonical(&mut self, canonical: bool) {
+        if canonical {
+            self.0.c_lflag |= libc::ICANON;
+        } else {
+            self.0.c_lflag &= !libc::ICANON;
+        }
+    }
+
+    /// Disables all input and output processing, echo and signal generation (`cfmakeraw`).
+    pub fn make_raw(&mut self) {
+        unsafe { libc::cfmakeraw(&mut self.0) };
+    }
+
+    /// The underlying `termios` struct.
+    pub fn raw(&self) -> &libc::termios {
+        &self.0
+    }
+
+    pub fn raw_mut(&mut self) -> &mut libc::termios {
+        &mut self.0
+    }
+}
+
+impl fmt::Debug for Termios {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Termios")
+            .field("iflag", &self.0.c_iflag)
+            .field("oflag", &self.0.c_oflag)
+            .field("cflag", &self.0.c_cflag)
+            .field("lflag", &self.0.c_lflag)
+            .finish()
+    }
+}
+
+/// Pseudoterminal in raw mode, the previous attributes are restored on drop.
+pub struct RawMode<'a> {
+    pty:   &'a mut Pty,
+    saved: Termios,
+}
+
+impl<'a> RawMode<'a> {
+    /// Restores the previous attributes.
+    pub fn restore(self) -> Result<()> {
+        let r = self.pty.set_attributes(&self.saved, SetWhen::Now);
+
+        // Already restored
+        mem::forget(self);
+
+        r
+    }
+}
+
+impl<'a> Deref for RawMode<'a> {
+    type Target = Pty;
+
+    fn deref(&self) -> &Pty {
+        self.pty
+    }
+}
+
+impl<'a> DerefMut for RawMode<'a> {
+    fn deref_mut(&mut self) -> &mut Pty {
+        self.pty
+    }
+}
+
+impl<'a> Drop for RawMode<'a> {
+    fn drop(&mut self) {
+        // ignore error
+        let _ = self.pty.set_attributes(&self.saved, SetWhen::Now);
+    }
+}
+
+impl Pty {
+    /// Reads the terminal attributes (`tcgetattr`), the master reports the attributes of the
+    /// slave.
+    pub fn get_attributes(&self) -> Result<Termios> {
+        let mut t = unsafe { mem::zeroed() };
+
+        match unsafe { libc::tcgetattr(self.as_raw_fd(), &mut t) } {
+            -1 => Err(Error::last_os_error()),
+            _  => Ok(Termios(t)),
+        }
+    }
+
+    /// Sets the terminal attributes (`tcsetattr`).
+    pub fn set_attributes(&mut self, t: &Termios, when: SetWhen) -> Result<()> {
+        match unsafe { libc::tcsetattr(self.as_raw_fd(), when.into(), &t.0) } {
+            -1 => Err(Error::last_os_error()),
+            _  => Ok(()),
+        }
+    }
+
+    /// Puts the terminal in raw mode until the returned guard is dropped.
+    pub fn raw_mode(&mut self) -> Result<RawMode> {
+        let saved   = try!(self.get_attributes());
+        let mut raw = saved;
+
+        raw.make_raw();
+
+        try!(self.set_attributes(&raw, SetWhen::Now));
+
+        Ok(RawMode {
+            pty:   self,
+            saved: saved,
+        })
+    }
+
+    /// If the application is reading a line without echo, usually a password prompt.
+    pub fn password_input(&self) -> Result<bool> {
+        self.get_attributes().map(|t| t.canonical() && !t.echo())
+    }
+}
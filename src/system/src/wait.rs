@@ -0,0 +1,94 @@
+//! Reaping of child processes.
+
+use std::fmt;
+use std::io::{Error, Result};
+
+use libc;
+
+use ::ProcessId;
+
+/// How a child process terminated.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ExitStatus {
+    /// Exited normally with the given exit code
+    Exited(i32),
+    /// Terminated by the given signal number
+    Signaled(i32),
+}
+
+impl ExitStatus {
+    fn from_raw(status: libc::c_int) -> Option<Self> {
+        if libc::WIFEXITED(status) {
+            Some(ExitStatus::Exited(libc::WEXITSTATUS(status)))
+        } else if libc::WIFSIGNALED(status) {
+            Some(ExitStatus::Signaled(libc::WTERMSIG(status)))
+        } else {
+            // Stopped or continued
+            None
+        }
+    }
+
+    /// Exit code if the process exited normally.
+    pub fn code(&self) -> Option<i32> {
+        match *self {
+            ExitStatus::Exited(c)   => Some(c),
+            ExitStatus::Signaled(_) => None,
+        }
+    }
+
+    /// Signal number if the process was terminated by a signal.
+    pub fn signal(&self) -> Option<i32> {
+        match *self {
+            ExitStatus::Exited(_)   => None,
+            ExitStatus::Signaled(s) => Some(s),
+        }
+    }
+
+    /// If the process exited normally with exit code 0.
+    pub fn success(&self) -> bool {
+        *self == ExitStatus::Exited(0)
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExitStatus::Exited(c)   => write!(f, "exit code: {}", c),
+            ExitStatus::Signaled(s) => write!(f, "signal: {}", s),
+        }
+    }
+}
+
+fn waitpid(pid: ProcessId, options: libc::c_int) -> Result<Option<ExitStatus>> {
+    let mut status = 0;
+
+    loop {
+        match unsafe { libc::waitpid(pid.0, &mut status, options) } {
+            -1 => {
+                let e = Error::last_os_error();
+
+                if e.raw_os_error() != Some(libc::EINTR) {
+                    return Err(e);
+                }
+            },
+            // Still running (WNOHANG)
+            0  => return Ok(None),
+            _  => match ExitStatus::from_raw(status) {
+                Some(s) => return Ok(Some(s)),
+                None    => if options & libc::WNOHANG == libc::WNOHANG {
+                    return Ok(None);
+                },
+            },
+        }
+    }
+}
+
+/// Blocks until the child process `pid` has terminated and reaps it.
+pub fn wait(pid: ProcessId) -> Result<ExitStatus> {
+    waitpid(pid, 0).map(|s| s.expect("waitpid without WNOHANG returned without status"))
+}
+
+/// Reaps the child process `pid` if it has terminated, returns `Ok(None)` if it is still running.
+pub fn try_wait(pid: ProcessId) -> Result<Option<ExitStatus>> {
+    waitpid(pid, libc::WNOHANG)
+}
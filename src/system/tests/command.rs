@@ -0,0 +1,68 @@
+//! File descriptors passed to processes spawned in a pseudoterminal.
+
+extern crate libc;
+extern crate cu2o_system;
+
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+use cu2o_system::PtyCommand;
+
+/// Creates a pipe, neither end is close-on-exec.
+#[cfg(target_os = "linux")]
+fn pipe() -> (File, File) {
+    let mut fds = [0; 2];
+
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+    unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+}
+
+/// Runs a child writing to the write end of a pipe, returns what was read from the pipe.
+#[cfg(target_os = "linux")]
+fn child_writes_to_pipe(inherit: bool) -> String {
+    let (mut reader, writer) = pipe();
+    let mut cmd              = PtyCommand::new("/bin/sh");
+
+    // Some shells only support single digit file descriptors in redirections
+    cmd.arg("-c").arg(format!("echo hi > /proc/self/fd/{}", writer.as_raw_fd()));
+
+    if inherit {
+        cmd.inherit_fd(writer.as_raw_fd());
+    }
+
+    let mut child = cmd.spawn().unwrap();
+
+    // Only the child's copy is left
+    drop(writer);
+
+    child.wait().unwrap();
+
+    let mut data = String::new();
+
+    reader.read_to_string(&mut data).unwrap();
+
+    data
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn inherited_fd_is_open() {
+    assert_eq!(child_writes_to_pipe(true), "hi\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn other_fds_are_closed() {
+    assert_eq!(child_writes_to_pipe(false), "");
+}
+
+#[test]
+fn standard_fds_cannot_be_inherited() {
+    for fd in 0..3 {
+        let e = PtyCommand::new("/bin/true").inherit_fd(fd).spawn().unwrap_err();
+
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    }
+}
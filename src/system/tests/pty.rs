@@ -0,0 +1,136 @@
+//! Reading from and writing to a pseudoterminal pair.
+
+extern crate libc;
+extern crate cu2o_system;
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::thread;
+
+use cu2o_system::Pty;
+
+fn raw_pair() -> (Pty, Pty) {
+    let (master, mut slave) = Pty::new().unwrap();
+    let mut t               = slave.get_attributes().unwrap();
+
+    // Pass data through unchanged
+    t.make_raw();
+
+    slave.set_attributes(&t, cu2o_system::SetWhen::Now).unwrap();
+
+    (master, slave)
+}
+
+/// Waits for data from the line discipline, which passes it on asynchronously.
+fn wait_readable(pty: &Pty) {
+    let mut fd = libc::pollfd {
+        fd:      pty.as_raw_fd(),
+        events:  libc::POLLIN,
+        revents: 0,
+    };
+
+    assert_eq!(unsafe { libc::poll(&mut fd, 1, 5000) }, 1, "no data within 5 seconds");
+}
+
+#[test]
+fn nonblocking_read_would_block() {
+    let (mut master, _slave) = raw_pair();
+    let mut buf              = [0; 16];
+
+    master.set_nonblocking(true).unwrap();
+
+    assert_eq!(master.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn nonblocking_read_returns_data() {
+    let (mut master, mut slave) = raw_pair();
+    let mut buf                 = [0; 16];
+
+    master.set_nonblocking(true).unwrap();
+    slave.write_all(b"foo").unwrap();
+
+    wait_readable(&master);
+
+    assert_eq!(master.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], b"foo");
+    assert_eq!(master.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn blocking_can_be_restored() {
+    let (mut master, mut slave) = raw_pair();
+    let mut buf                 = [0; 16];
+
+    master.set_nonblocking(true).unwrap();
+    master.set_nonblocking(false).unwrap();
+
+    assert_eq!(unsafe { libc::fcntl(master.as_raw_fd(), libc::F_GETFL) } & libc::O_NONBLOCK, 0);
+
+    let writer = thread::spawn(move || {
+        slave.write_all(b"bar").unwrap();
+
+        slave
+    });
+
+    // Blocks until the data arrives
+    assert_eq!(master.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], b"bar");
+
+    writer.join().unwrap();
+}
+
+#[test]
+fn hangup_is_end_of_stream() {
+    let (mut master, slave) = raw_pair();
+    let mut buf             = [0; 16];
+
+    drop(slave);
+
+    assert_eq!(master.read(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn hangup_after_data_is_end_of_stream() {
+    let (mut master, mut slave) = raw_pair();
+    let mut data                = Vec::new();
+
+    slave.write_all(b"baz").unwrap();
+
+    drop(slave);
+
+    master.read_to_end(&mut data).unwrap();
+
+    assert_eq!(data, b"baz");
+}
+
+#[test]
+fn clone_has_its_own_fd() {
+    let (master, mut slave) = raw_pair();
+    let mut clone           = master.try_clone().unwrap();
+    let mut buf             = [0; 16];
+
+    assert!(clone.as_raw_fd() != master.as_raw_fd());
+    assert!(unsafe { libc::fcntl(clone.as_raw_fd(), libc::F_GETFD) } & libc::FD_CLOEXEC != 0);
+
+    // Dropping the original leaves the clone open
+    drop(master);
+
+    slave.write_all(b"qux").unwrap();
+
+    wait_readable(&clone);
+
+    assert_eq!(clone.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], b"qux");
+}
+
+#[test]
+fn clone_shares_nonblocking_mode() {
+    let (mut master, _slave) = raw_pair();
+    let mut clone            = master.try_clone().unwrap();
+    let mut buf              = [0; 16];
+
+    master.set_nonblocking(true).unwrap();
+
+    assert_eq!(clone.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
@@ -0,0 +1,72 @@
+//! Terminal attributes through a pseudoterminal.
+
+extern crate cu2o_system;
+
+use std::io::{Read, Write};
+
+use cu2o_system::{Pty, SetWhen};
+
+#[test]
+fn slave_starts_in_cooked_mode() {
+    let (master, _slave) = Pty::new().unwrap();
+    let t                = master.get_attributes().unwrap();
+
+    assert!(t.echo());
+    assert!(t.canonical());
+    assert!(!master.password_input().unwrap());
+}
+
+#[test]
+fn master_sees_slave_attributes() {
+    let (master, mut slave) = Pty::new().unwrap();
+    let mut t               = slave.get_attributes().unwrap();
+
+    t.set_echo(false);
+
+    slave.set_attributes(&t, SetWhen::Now).unwrap();
+
+    assert!(!master.get_attributes().unwrap().echo());
+    assert!(master.get_attributes().unwrap().canonical());
+    assert!(master.password_input().unwrap());
+}
+
+#[test]
+fn raw_mode_is_restored() {
+    let (master, mut slave) = Pty::new().unwrap();
+
+    {
+        let raw = slave.raw_mode().unwrap();
+        let t   = raw.get_attributes().unwrap();
+
+        assert!(!t.echo());
+        assert!(!t.canonical());
+        assert!(!master.password_input().unwrap());
+    }
+
+    let t = slave.get_attributes().unwrap();
+
+    assert!(t.echo());
+    assert!(t.canonical());
+}
+
+#[test]
+fn raw_mode_restore() {
+    let (_master, mut slave) = Pty::new().unwrap();
+
+    slave.raw_mode().unwrap().restore().unwrap();
+
+    assert!(slave.get_attributes().unwrap().canonical());
+}
+
+#[test]
+fn raw_mode_passes_data_unchanged() {
+    let (mut master, mut slave) = Pty::new().unwrap();
+    let mut raw                 = slave.raw_mode().unwrap();
+    let mut buf                 = [0; 4];
+
+    // No line buffering, no CR translation
+    master.write_all(b"a\rb\x03").unwrap();
+    raw.read_exact(&mut buf).unwrap();
+
+    assert_eq!(&buf, b"a\rb\x03");
+}
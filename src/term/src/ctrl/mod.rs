@@ -31,12 +31,15 @@ pub use self::sequences::{
     Charset,
     CharsetIndex,
     Color,
+    CursorStyle,
     EraseInDisplay,
     EraseInLine,
+    KeyboardFlagsMode,
     KeypadMode,
     Mode,
     PrivateMode,
     Seq,
+    WindowReport,
 };
 
 pub enum Error {
@@ -44,6 +47,7 @@ pub enum Error {
     UnknownCharset(u8, Option<u8>),
     UnknownCSI(u8, Vec<u8>),
     UnknownOSC(Vec<u8>),
+    UnknownDCS(Vec<u8>),
     UnknownEscapeChar(u8),
     UnknownSetReset(usize),
     UnknownSetResetData(Vec<u8>),
@@ -61,6 +65,7 @@ impl fmt::Debug for Error {
         match *self {
             UnknownCSI(c, ref data)           => write!(f, "Unknown control sequence: {:?} {:?}", c as char, String::from_utf8_lossy(data)),
             UnknownOSC(ref data)              => write!(f, "Unknown operating system command: {:?}", String::from_utf8_lossy(data)),
+            UnknownDCS(ref data)              => write!(f, "Unknown device control string: {:?}", String::from_utf8_lossy(data)),
             UnknownCharset(c, None)           => write!(f, "Unknown charset sequence: {:?}", c),
             UnknownCharset(c, Some(d))        => write!(f, "Unknown charset sequence: {:?} {:?}", c, d),
             UnknownEscapeChar(c)              => write!(f, "Unknown escape character: {:?}", c),
@@ -83,6 +88,7 @@ impl fmt::Display for Error {
         match *self {
             UnknownCSI(c, ref data)           => write!(f, "Unknown control sequence: {:?} {:?}", c as char, String::from_utf8_lossy(data)),
             UnknownOSC(ref data)              => write!(f, "Unknown operating system command: {:?}", String::from_utf8_lossy(data)),
+            UnknownDCS(ref data)              => write!(f, "Unknown device control string: {:?}", String::from_utf8_lossy(data)),
             UnknownCharset(c, None)           => write!(f, "Unknown charset sequence: {:?}", c),
             UnknownCharset(c, Some(d))        => write!(f, "Unknown charset sequence: {:?} {:?}", c, d),
             UnknownEscapeChar(c)              => write!(f, "Unknown escape character: {:?}", c),
@@ -152,7 +158,7 @@ fn parse_esc(m: Input<u8>) -> ParseResult<u8, Seq, Error> {
         b'M'  => m.ret(Seq::ReverseIndex), /* RI */
         b'N'  => m.ret(Seq::SingleShiftSelectG2CharSet), /* SS2 */
         b'O'  => m.ret(Seq::SingleShiftSelectG3CharSet), /* SS3 */
-        b'P'  => m.ret(Seq::DeviceControlString), /* DCS */
+        b'P'  => parse_dcs(m), /* DCS */
         b'V'  => m.ret(Seq::StartOfGuardedArea), /* SPA */
         b'W'  => m.ret(Seq::EndOfGuardedArea), /* EPA */
         b'X'  => m.ret(Seq::StartOfString), /* SOS */
@@ -181,6 +187,7 @@ fn parse_csi(m: Input<u8>) -> ParseResult<u8, Seq, Error> {
             // Identify terminal
             b'c' => match buf.get(0) {
                 Some(&b'>') => m.ret(Seq::SendSecondaryDeviceAttributes),
+                Some(&b'=') => m.ret(Seq::SendTertiaryDeviceAttributes),
                 _           => m.ret(Seq::SendPrimaryDeviceAttributes),
             },
             b'd' => m.from_result(parse_int(buf).map(|n| Seq::LinePositionAbsolute(n.unwrap_or(1)))),
@@ -202,6 +209,10 @@ fn parse_csi(m: Input<u8>) -> ParseResult<u8, Seq, Error> {
             // No parameters equals ``CSI 0 m`` which means Reset
             b'm' => if buf.len() == 0 {
                 m.ret(Seq::CharAttr(vec![CharAttr::Reset]))
+            } else if buf[0] == b'>' {
+                m.from_result(parse_modify_keys(&buf[1..]))
+            } else if buf == b"?4" {
+                m.ret(Seq::QueryModifyOtherKeys)
             } else {
                 // TODO: Check for empty buf at end?
                 m.from_result(parse_only(|i| sep_by1(i, parse_char_attr, |i| token(i, b';')), buf)
@@ -212,12 +223,51 @@ fn parse_csi(m: Input<u8>) -> ParseResult<u8, Seq, Error> {
                 Ok(Some(6)) => m.ret(Seq::CursorPositionReport),
                 _           => m.err(Error::UnknownCSI(b'n', From::from(buf))),
             },
+            // Request mode
+            b'p' => match (buf.first(), buf.last()) {
+                (Some(&b'?'), Some(&b'$')) if buf.len() > 1 =>
+                    m.from_result(parse_int(&buf[1..buf.len() - 1]).map(|n| Seq::RequestPrivateMode(n.unwrap_or(0)))),
+                _ => m.err(Error::UnknownCSI(b'p', From::from(buf))),
+            },
+            b'q' => match buf.get(0) {
+                Some(&b'>') => match parse_int(&buf[1..]) {
+                    Ok(None) | Ok(Some(0)) => m.ret(Seq::RequestTerminalVersion),
+                    _                      => m.err(Error::UnknownCSI(b'q', From::from(buf))),
+                },
+                _ if buf.last() == Some(&b' ') => match parse_int(&buf[..buf.len() - 1]) {
+                    Ok(None)    |
+                    Ok(Some(0)) |
+                    Ok(Some(1)) => m.ret(Seq::SetCursorStyle(CursorStyle::BlinkingBlock)),
+                    Ok(Some(2)) => m.ret(Seq::SetCursorStyle(CursorStyle::SteadyBlock)),
+                    Ok(Some(3)) => m.ret(Seq::SetCursorStyle(CursorStyle::BlinkingUnderline)),
+                    Ok(Some(4)) => m.ret(Seq::SetCursorStyle(CursorStyle::SteadyUnderline)),
+                    Ok(Some(5)) => m.ret(Seq::SetCursorStyle(CursorStyle::BlinkingBar)),
+                    Ok(Some(6)) => m.ret(Seq::SetCursorStyle(CursorStyle::SteadyBar)),
+                    _           => m.err(Error::UnknownCSI(b'q', From::from(buf))),
+                },
+                _           => m.err(Error::UnknownCSI(b'q', From::from(buf))),
+            },
             b'r' => m.from_result(parse_only(parser!{
                     let top = maybe(decimal);
                     let bot = maybe(parser!{token(b';'); decimal()});
 
                     ret @ _, Error: Seq::ScrollingRegion(top, bot)
                 }, buf).map_err(|_| Error::UnknownCSI(b'r', buf.to_owned()))),
+            b't' => match parse_int(buf) {
+                Ok(Some(14)) => m.ret(Seq::WindowReport(WindowReport::TextAreaPixels)),
+                Ok(Some(16)) => m.ret(Seq::WindowReport(WindowReport::CellPixels)),
+                Ok(Some(18)) => m.ret(Seq::WindowReport(WindowReport::TextAreaCells)),
+                Ok(Some(19)) => m.ret(Seq::WindowReport(WindowReport::ScreenCells)),
+                _            => m.err(Error::UnknownCSI(b't', From::from(buf))),
+            },
+            // Kitty keyboard protocol
+            b'u' => match buf.get(0) {
+                Some(&b'>') => m.from_result(parse_int(&buf[1..]).map(|n| Seq::PushKeyboardFlags(n.unwrap_or(0) as u32))),
+                Some(&b'<') => m.from_result(parse_int(&buf[1..]).map(|n| Seq::PopKeyboardFlags(n.unwrap_or(1)))),
+                Some(&b'?') if buf.len() == 1 => m.ret(Seq::QueryKeyboardFlags),
+                Some(&b'=') => m.from_result(parse_keyboard_flags(&buf[1..])),
+                _           => m.err(Error::UnknownCSI(b'u', From::from(buf))),
+            },
             b'A' => m.from_result(parse_int(buf).map(|n| Seq::CursorUp(n.unwrap_or(1)))),
             b'B' => m.from_result(parse_int(buf).map(|n| Seq::CursorDown(n.unwrap_or(1)))),
             b'C' => m.from_result(parse_int(buf).map(|n| Seq::CursorForward(n.unwrap_or(1)))),
@@ -251,6 +301,29 @@ fn parse_csi(m: Input<u8>) -> ParseResult<u8, Seq, Error> {
     )
 }
 
+/// Parses the parameters of ``CSI > resource ; value m``, only modifyOtherKeys (4) is supported.
+fn parse_modify_keys(buf: &[u8]) -> Result<Seq, Error> {
+    let mut params = buf.splitn(2, |&c| c == b';');
+
+    match try!(parse_int(params.next().unwrap_or(b""))) {
+        Some(4) => parse_int(params.next().unwrap_or(b"")).map(|n| Seq::SetModifyOtherKeys(n.unwrap_or(0))),
+        _       => Err(Error::UnknownCSI(b'm', buf.to_owned())),
+    }
+}
+
+/// Parses the parameters of ``CSI = flags ; mode u``.
+fn parse_keyboard_flags(buf: &[u8]) -> Result<Seq, Error> {
+    let mut params = buf.splitn(2, |&c| c == b';');
+    let flags      = try!(parse_int(params.next().unwrap_or(b""))).unwrap_or(0) as u32;
+
+    match try!(parse_int(params.next().unwrap_or(b""))) {
+        None | Some(1) => Ok(Seq::SetKeyboardFlags(flags, KeyboardFlagsMode::Set)),
+        Some(2)        => Ok(Seq::SetKeyboardFlags(flags, KeyboardFlagsMode::Union)),
+        Some(3)        => Ok(Seq::SetKeyboardFlags(flags, KeyboardFlagsMode::Difference)),
+        _              => Err(Error::UnknownCSI(b'u', buf.to_owned())),
+    }
+}
+
 /// Attempts to parse an operating system command from the given buffer.
 fn parse_osc(i: Input<u8>) -> ParseResult<u8, Seq, Error> {
     // ``ESC \`` = ``ST``
@@ -275,6 +348,60 @@ fn parse_osc(i: Input<u8>) -> ParseResult<u8, Seq, Error> {
     }
 }
 
+/// Attempts to parse a device control string from the given buffer.
+fn parse_dcs(i: Input<u8>) -> ParseResult<u8, Seq, Error> {
+    // Only ``ESC \`` is a valid string terminator for DCS
+    parse!{i;
+        let buf = scan(0, |prev, c| if c == 0x5C && prev == 0x1B { None } else { Some(c) });
+        // We have \ left, get rid of it
+        take(1);
+        i -> {
+            // Strip ESC
+            let buf = &buf[.. buf.len().saturating_sub(1)];
+
+            if buf.starts_with(b"$q") {
+                i.ret(Seq::RequestStatusString(buf[2..].to_owned()))
+            } else if buf.starts_with(b"+q") {
+                match parse_termcap_names(&buf[2..]) {
+                    Some(names) => i.ret(Seq::RequestTermcap(names)),
+                    None        => i.err(Error::UnknownDCS(From::from(buf))),
+                }
+            } else {
+                i.ret(Seq::DeviceControlString(buf.to_owned()))
+            }
+        }
+    }
+}
+
+/// Decodes the ``;``-separated list of hex-encoded capability names from XTGETTCAP.
+fn parse_termcap_names(buf: &[u8]) -> Option<Vec<String>> {
+    fn hex(c: u8) -> Option<u8> {
+        match c {
+            b'0'...b'9' => Some(c - b'0'),
+            b'a'...b'f' => Some(c - b'a' + 10),
+            b'A'...b'F' => Some(c - b'A' + 10),
+            _           => None,
+        }
+    }
+
+    buf.split(|&c| c == b';').map(|name| {
+        if name.is_empty() || name.len() % 2 != 0 {
+            return None;
+        }
+
+        let mut s = Vec::with_capacity(name.len() / 2);
+
+        for pair in name.chunks(2) {
+            match (hex(pair[0]), hex(pair[1])) {
+                (Some(h), Some(l)) => s.push(h << 4 | l),
+                _                  => return None,
+            }
+        }
+
+        String::from_utf8(s).ok()
+    }).collect()
+}
+
 fn parse_charset(m: Input<u8>, index: CharsetIndex) -> ParseResult<u8, Seq, Error> {
     any(m).bind(|m, c| match c {
         b'0' => m.ret(Seq::Charset(index, Charset::DECSpecialAndLineDrawing)),
@@ -330,32 +457,46 @@ fn parse_mode(buffer: &[u8]) -> Result<Vec<Mode>, Error> {
 ///
 /// Expects to receive data after the sequences ``ESC [ ?`` but before ``h`` or ``l``.
 fn parse_private_mode(buffer: &[u8]) -> Result<Vec<PrivateMode>, Error> {
-    use self::PrivateMode::*;
-
     chomp::parse_only(|i| sep_by1(i, parser!{
         let n = decimal();
 
-        i -> match n {
-            1    => i.ret(ApplicationCursorKeys),
-            5    => i.ret(LightScreen),
-            7    => i.ret(Autowrap),
-            8    => i.ret(Autorepeat),
-            12   => i.ret(CursorBlink),
-            25   => i.ret(ShowCursor),
-            47   => i.ret(AlternateScreenBuffer),
-            1000 => i.ret(MouseTrackingX11),
-            1002 => i.ret(MouseTrackingCell),
-            1005 => i.ret(MouseModeUtf8),
-            1006 => i.ret(MouseModeSGR),
-            1004 => i.ret(SendFocusEvents),
-            1047 => i.ret(AlternateScreenBuffer),
-            1048 => i.ret(SaveCursor),
-            1049 => i.ret(SaveCursorAlternateBufferClear),
-            n    => i.err(Error::UnknownPrivateSetReset(n)),
+        i -> match private_mode(n) {
+            Some(m) => i.ret(m),
+            None    => i.err(Error::UnknownPrivateSetReset(n)),
         }
     }, |i| token(i, b';')), buffer).map_err(|_| Error::UnknownPrivateSetResetData(buffer.to_owned()))
 }
 
+/// Maps a private mode number to the private mode, if it is known.
+pub fn private_mode(n: usize) -> Option<PrivateMode> {
+    use self::PrivateMode::*;
+
+    match n {
+        1    => Some(ApplicationCursorKeys),
+        5    => Some(LightScreen),
+        7    => Some(Autowrap),
+        8    => Some(Autorepeat),
+        9    => Some(MouseTrackingX10),
+        12   => Some(CursorBlink),
+        25   => Some(ShowCursor),
+        47   => Some(AlternateScreenBuffer),
+        1000 => Some(MouseTrackingX11),
+        1002 => Some(MouseTrackingCell),
+        1003 => Some(MouseTrackingAny),
+        1004 => Some(SendFocusEvents),
+        1005 => Some(MouseModeUtf8),
+        1006 => Some(MouseModeSGR),
+        1015 => Some(MouseModeUrxvt),
+        1016 => Some(MouseModeSGRPixels),
+        1047 => Some(AlternateScreenBuffer),
+        1048 => Some(SaveCursor),
+        1049 => Some(SaveCursorAlternateBufferClear),
+        2004 => Some(BracketedPaste),
+        2026 => Some(SynchronizedOutput),
+        _    => None,
+    }
+}
+
 /// Parses a single character attribute
 ///
 /// Expects to receive data after the sequence ``ESC [`` but before ``m``,
@@ -459,3 +600,51 @@ fn parse_int(buf: &[u8]) -> Result<Option<usize>, Error> {
      .map(|i| Some(i))
      .map_err(|e| Error::IntParseError(e))
 }
+
+#[cfg(test)]
+mod tests {
+    use chomp::buffer::{SliceStream, Stream};
+
+    use super::{parser, parse_termcap_names, Seq};
+
+    fn parse(data: &[u8]) -> Seq {
+        SliceStream::new(data).parse(parser).unwrap()
+    }
+
+    #[test]
+    fn termcap_names_are_hex_decoded() {
+        assert_eq!(parse_termcap_names(b"544E"), Some(vec!["TN".to_owned()]));
+        assert_eq!(parse_termcap_names(b"636f6c6f7273;524742"), Some(vec!["colors".to_owned(), "RGB".to_owned()]));
+    }
+
+    #[test]
+    fn invalid_termcap_names_are_rejected() {
+        // Empty name, odd length, not hex
+        assert_eq!(parse_termcap_names(b""), None);
+        assert_eq!(parse_termcap_names(b"544E;"), None);
+        assert_eq!(parse_termcap_names(b"544"), None);
+        assert_eq!(parse_termcap_names(b"5G4E"), None);
+    }
+
+    #[test]
+    fn xtgettcap() {
+        assert_eq!(parse(b"\x1BP+q544E;636F6C6F7273\x1B\\"), Seq::RequestTermcap(vec!["TN".to_owned(), "colors".to_owned()]));
+    }
+
+    #[test]
+    fn decrqss() {
+        assert_eq!(parse(b"\x1BP$qm\x1B\\"), Seq::RequestStatusString(b"m".to_vec()));
+        assert_eq!(parse(b"\x1BP$q q\x1B\\"), Seq::RequestStatusString(b" q".to_vec()));
+    }
+
+    #[test]
+    fn unknown_dcs_is_collected_up_to_st() {
+        assert_eq!(parse(b"\x1BPfoo\x07bar\x1B\\"), Seq::DeviceControlString(b"foo\x07bar".to_vec()));
+    }
+
+    #[test]
+    fn decrqm() {
+        assert_eq!(parse(b"\x1B[?2004$p"), Seq::RequestPrivateMode(2004));
+        assert_eq!(parse(b"\x1B[?$p"), Seq::RequestPrivateMode(0));
+    }
+}
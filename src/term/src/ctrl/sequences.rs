@@ -22,7 +22,9 @@ pub enum Seq {
     ReverseIndex,
     SingleShiftSelectG2CharSet,
     SingleShiftSelectG3CharSet,
-    DeviceControlString,
+    /// Device control string (DCS) not otherwise recognized, contains the data up to but not
+    /// including the string terminator.
+    DeviceControlString(Vec<u8>),
     StartOfGuardedArea,
     EndOfGuardedArea,
     StartOfString,
@@ -88,6 +90,52 @@ pub enum Seq {
     ScrollingRegion(Option<usize>, Option<usize>),
     SendPrimaryDeviceAttributes,
     SendSecondaryDeviceAttributes,
+    /// Request the terminal unit id (DA3), ``CSI = c``.
+    ///
+    /// Report format: ``DCS ! | D...D ST`` where ``D...D`` is a hex-encoded unit id.
+    SendTertiaryDeviceAttributes,
+    /// Request the terminal name and version (XTVERSION), ``CSI > q``.
+    ///
+    /// Report format: ``DCS > | name(version) ST``.
+    RequestTerminalVersion,
+    /// Request the state of a private mode by number (DECRQM), ``CSI ? Ps $ p``.
+    ///
+    /// Report format: ``ESC [ ? Ps ; Pm $ y`` where ``Pm`` is 0 for not recognized, 1 for set, 2
+    /// for reset, 3 for permanently set and 4 for permanently reset.
+    RequestPrivateMode(usize),
+    /// Set cursor style (DECSCUSR), ``CSI Ps SP q``.
+    SetCursorStyle(CursorStyle),
+    /// Window manipulation (XTWINOPS), only the size-reporting operations are supported.
+    WindowReport(WindowReport),
+    /// Push the current kitty keyboard protocol flags onto the stack and set new flags,
+    /// ``CSI > flags u``.
+    PushKeyboardFlags(u32),
+    /// Pop n entries from the kitty keyboard protocol flag stack, ``CSI < n u``.
+    PopKeyboardFlags(usize),
+    /// Request the current kitty keyboard protocol flags, ``CSI ? u``.
+    ///
+    /// Report format: ``ESC [ ? flags u``.
+    QueryKeyboardFlags,
+    /// Modify the current kitty keyboard protocol flags, ``CSI = flags ; mode u``.
+    SetKeyboardFlags(u32, KeyboardFlagsMode),
+    /// Set the xterm modifyOtherKeys level (XTMODKEYS), ``CSI > 4 ; Ps m``, omitting ``Ps``
+    /// resets to 0.
+    SetModifyOtherKeys(usize),
+    /// Request the xterm modifyOtherKeys level (XTQMODKEYS), ``CSI ? 4 m``.
+    ///
+    /// Report format: ``ESC [ > 4 ; Ps m``.
+    QueryModifyOtherKeys,
+    /* DCS */
+    /// Request selection or setting (DECRQSS), ``DCS $ q Pt ST``, contains ``Pt``.
+    ///
+    /// Report format: ``DCS 1 $ r Pt ST`` for valid requests, ``DCS 0 $ r ST`` for invalid.
+    RequestStatusString(Vec<u8>),
+    /// Request termcap/terminfo strings (XTGETTCAP), ``DCS + q Pt ST``, contains the decoded
+    /// capability names.
+    ///
+    /// Report format: ``DCS 1 + r Pt ST`` where ``Pt`` is the hex-encoded ``name=value``, or
+    /// ``DCS 0 + r Pt ST`` where ``Pt`` is the hex-encoded unknown name.
+    RequestTermcap(Vec<String>),
     /* OSC */
     SetWindowTitle(String),
     SetIconName(String),
@@ -96,6 +144,53 @@ pub enum Seq {
     LinePositionAbsolute(usize),
 }
 
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum WindowReport {
+    /// Report the size of the text area in pixels, ``CSI 14 t``.
+    ///
+    /// Report format: ``ESC [ 4 ; h ; w t``.
+    TextAreaPixels,
+    /// Report the size of a character cell in pixels, ``CSI 16 t``.
+    ///
+    /// Report format: ``ESC [ 6 ; h ; w t``.
+    CellPixels,
+    /// Report the size of the text area in characters, ``CSI 18 t``.
+    ///
+    /// Report format: ``ESC [ 8 ; h ; w t``.
+    TextAreaCells,
+    /// Report the size of the screen in characters, ``CSI 19 t``.
+    ///
+    /// Report format: ``ESC [ 9 ; h ; w t``.
+    ScreenCells,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CursorStyle {
+    /// ``CSI 0 SP q`` or ``CSI 1 SP q``, default.
+    BlinkingBlock,
+    /// ``CSI 2 SP q``
+    SteadyBlock,
+    /// ``CSI 3 SP q``
+    BlinkingUnderline,
+    /// ``CSI 4 SP q``
+    SteadyUnderline,
+    /// ``CSI 5 SP q``
+    BlinkingBar,
+    /// ``CSI 6 SP q``
+    SteadyBar,
+}
+
+/// How ``CSI = flags ; mode u`` modifies the current kitty keyboard protocol flags.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum KeyboardFlagsMode {
+    /// Replace the flags, mode 1 (default).
+    Set,
+    /// Set the given bits, mode 2.
+    Union,
+    /// Clear the given bits, mode 3.
+    Difference,
+}
+
 #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum KeypadMode {
     Numeric,
@@ -143,6 +238,10 @@ pub enum PrivateMode {
     ///
     /// Default: off
     SendFocusEvents,
+    /// X10 compatibility mouse tracking, only button presses are reported.
+    ///
+    /// Default: off
+    MouseTrackingX10,
     /// X11 xterm mouse protocol tracking.
     ///
     /// Default: off
@@ -151,6 +250,10 @@ pub enum PrivateMode {
     ///
     /// Defualt: off
     MouseTrackingCell,
+    /// All motion mouse tracking.
+    ///
+    /// Default: off
+    MouseTrackingAny,
     /// UTF-8 Mouse mode
     ///
     /// Default: off
@@ -159,6 +262,22 @@ pub enum PrivateMode {
     ///
     /// Default: off
     MouseModeSGR,
+    /// Bracketed paste mode, pasted text is surrounded by ``ESC [ 200 ~`` and ``ESC [ 201 ~``.
+    ///
+    /// Default: off
+    BracketedPaste,
+    /// Synchronized output, the screen should not be redrawn until the mode is reset.
+    ///
+    /// Default: off
+    SynchronizedOutput,
+    /// urxvt Mouse mode
+    ///
+    /// Default: off
+    MouseModeUrxvt,
+    /// SGR Mouse mode with pixel coordinates
+    ///
+    /// Default: off
+    MouseModeSGRPixels,
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
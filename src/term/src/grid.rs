@@ -1,4 +1,5 @@
 use std::cmp;
+use std::collections::VecDeque;
 use std::fmt;
 
 bitflags! {
@@ -37,6 +38,21 @@ impl Cursor {
     pub fn col(&self) -> usize {
         self.col
     }
+
+    /// If the cursor automatically moves to the next line when writing past the end of line.
+    #[inline]
+    pub fn autowrap(&self) -> bool {
+        self.state.contains(AUTOWRAP)
+    }
+
+    #[inline]
+    pub fn set_autowrap(&mut self, autowrap: bool) {
+        if autowrap {
+            self.state.insert(AUTOWRAP);
+        } else {
+            self.state.remove(AUTOWRAP | WRAP_NEXT);
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -54,6 +70,10 @@ pub struct Grid<T: Copy + Default> {
     screg:  ScrollRegion,
     /// Data stored in lines, columns
     data:   Vec<Vec<T>>,
+    /// Lines scrolled off the top of the screen, oldest first
+    scrollback: VecDeque<Vec<T>>,
+    /// Maximum number of lines kept in `scrollback`
+    scrollback_limit: usize,
 }
 
 impl<T: Copy + Default> Grid<T> {
@@ -68,6 +88,8 @@ impl<T: Copy + Default> Grid<T> {
             height: height,
             screg:  ScrollRegion { top: 0, bot: None },
             data:   data,
+            scrollback: VecDeque::new(),
+            scrollback_limit: 0,
         }
     }
 
@@ -98,6 +120,22 @@ impl<T: Copy + Default> Grid<T> {
         (self.width, self.height)
     }
 
+    /// Sets the maximum number of lines kept in the scrollback, dropping the oldest lines if
+    /// there are more.
+    pub fn set_scrollback_limit(&mut self, limit: usize) {
+        self.scrollback_limit = limit;
+
+        while self.scrollback.len() > limit {
+            self.scrollback.pop_front();
+        }
+    }
+
+    /// Lines scrolled off the top of the screen, oldest first.
+    #[inline]
+    pub fn scrollback(&self) -> &VecDeque<Vec<T>> {
+        &self.scrollback
+    }
+
     // Non-inclusive bottom margin
     fn screg_bot(&self) -> usize {
         if let Some(n) = self.screg.bot {
@@ -114,6 +152,17 @@ impl<T: Copy + Default> Grid<T> {
         let end   = len.saturating_sub(rows);
         let start = cmp::min(self.screg.top, end);
 
+        // Only lines leaving the screen are kept, not lines leaving a scroll region
+        if self.screg.top == 0 && self.scrollback_limit > 0 {
+            for row in &self.data[..cmp::min(rows, len)] {
+                if self.scrollback.len() >= self.scrollback_limit {
+                    self.scrollback.pop_front();
+                }
+
+                self.scrollback.push_back(row.clone());
+            }
+        }
+
         for i in start..end {
             self.data.swap(i, i + rows);
         }
@@ -172,6 +221,13 @@ impl<T: Copy + Default> Grid<T> {
         }
     }
 
+    /// Returns the current scroll region as (top, bottom), 0-indexed, top inclusive and bottom
+    /// exclusive.
+    #[inline]
+    pub fn scroll_region(&self) -> (usize, usize) {
+        (self.screg.top, self.screg_bot())
+    }
+
     pub fn set_scroll_region(&mut self, top: usize, bot: Option<usize>) {
         self.screg = ScrollRegion { top: top, bot: bot }
     }
@@ -356,3 +412,85 @@ impl Movement for Unbounded {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Grid;
+
+    /// 2 x 3 grid with each cell containing its row number, starting at 1.
+    fn numbered() -> Grid<usize> {
+        let mut g = Grid::new(2, 3);
+
+        for (i, row) in g.data.iter_mut().enumerate() {
+            for c in row.iter_mut() {
+                *c = i + 1;
+            }
+        }
+
+        g
+    }
+
+    fn scrollback(g: &Grid<usize>) -> Vec<Vec<usize>> {
+        g.scrollback().iter().cloned().collect()
+    }
+
+    #[test]
+    fn scrollback_disabled() {
+        let mut g = numbered();
+
+        g.scroll_down(1);
+
+        assert!(g.scrollback().is_empty());
+    }
+
+    #[test]
+    fn scrollback_keeps_lines_leaving_the_screen() {
+        let mut g = numbered();
+
+        g.set_scrollback_limit(10);
+        g.scroll_down(2);
+
+        assert_eq!(scrollback(&g), vec![vec![1, 1], vec![2, 2]]);
+        assert_eq!(g.data, vec![vec![3, 3], vec![0, 0], vec![0, 0]]);
+    }
+
+    #[test]
+    fn scrollback_limit_evicts_oldest() {
+        let mut g = numbered();
+
+        g.set_scrollback_limit(2);
+        g.scroll_down(1);
+        g.scroll_down(1);
+        g.scroll_down(1);
+
+        assert_eq!(scrollback(&g), vec![vec![2, 2], vec![3, 3]]);
+
+        g.set_scrollback_limit(1);
+
+        assert_eq!(scrollback(&g), vec![vec![3, 3]]);
+    }
+
+    #[test]
+    fn scroll_region_with_top_margin_is_not_kept() {
+        let mut g = numbered();
+
+        g.set_scrollback_limit(10);
+        g.set_scroll_region(1, None);
+        g.scroll_down(1);
+
+        assert!(g.scrollback().is_empty());
+        assert_eq!(g.data, vec![vec![1, 1], vec![3, 3], vec![0, 0]]);
+    }
+
+    #[test]
+    fn scroll_region_with_bottom_margin_is_kept() {
+        let mut g = numbered();
+
+        g.set_scrollback_limit(10);
+        g.set_scroll_region(0, Some(2));
+        g.scroll_down(1);
+
+        assert_eq!(scrollback(&g), vec![vec![1, 1]]);
+        assert_eq!(g.data, vec![vec![2, 2], vec![0, 0], vec![3, 3]]);
+    }
+}
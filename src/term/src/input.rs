@@ -0,0 +1,724 @@
+//! Encoding of keyboard input into the byte sequences sent to the application.
+
+use std::io;
+use std::io::Write;
+
+bitflags!{
+    pub flags Modifiers: u32 {
+        const SHIFT = 0b00000001,
+        const ALT   = 0b00000010,
+        const CTRL  = 0b00000100,
+        const SUPER = 0b00001000,
+
+        const NONE  = 0,
+    }
+}
+
+impl Default for Modifiers {
+    #[inline]
+    fn default() -> Self {
+        NONE
+    }
+}
+
+bitflags!{
+    /// Kitty keyboard protocol progressive enhancement flags.
+    pub flags KittyFlags: u32 {
+        /// Disambiguate escape codes, chords and keypad keys are sent as ``CSI u`` sequences.
+        const DISAMBIGUATE      = 0b00000001,
+        /// Report repeat and release events.
+        const REPORT_EVENTS     = 0b00000010,
+        /// Report the shifted key as an alternate key code.
+        const REPORT_ALTERNATES = 0b00000100,
+        /// Report all keys as escape codes, including text keys and modifier keys.
+        const REPORT_ALL        = 0b00001000,
+        /// Report the text produced by the key, requires `REPORT_ALL`.
+        const REPORT_TEXT       = 0b00010000,
+    }
+}
+
+impl Default for KittyFlags {
+    #[inline]
+    fn default() -> Self {
+        KittyFlags::empty()
+    }
+}
+
+impl Modifiers {
+    /// Returns the xterm modifier parameter, 1 + the sum of the active modifiers.
+    #[inline]
+    pub fn param(&self) -> u32 {
+        1 + self.bits()
+    }
+}
+
+/// Keyboard modes set by the application which affect the encoding of keys.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct KeyboardMode {
+    /// Application cursor keys (DECCKM), cursor keys send ``SS3`` sequences.
+    pub app_cursor: bool,
+    /// Application keypad (DECKPAM), keypad keys send ``SS3`` sequences instead of their text.
+    pub app_keypad: bool,
+    /// Active kitty keyboard protocol flags, legacy encoding is used if empty.
+    pub kitty:      KittyFlags,
+    /// xterm modifyOtherKeys level, 0 = disabled, 1 = only ambiguous chords, 2 = all chords.
+    pub modify_other_keys: u8,
+    /// Line feed/new line mode (LNM), Return sends ``CR LF`` instead of ``CR``.
+    pub new_line:   bool,
+}
+
+/// The kind of key event.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum KeyState {
+    Press,
+    /// Key is held down and repeated by the windowing system.
+    Repeat,
+    Release,
+}
+
+/// A key on the keyboard.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Key {
+    /// A key producing text, contains the character produced including the effect of shift.
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// Function key, F1 to F12.
+    F(u8),
+    /// Key on the numeric keypad producing text, one of ``0-9 + - * / . , =``.
+    Keypad(char),
+    /// Enter key on the numeric keypad.
+    KeypadEnter,
+    LeftShift,
+    LeftCtrl,
+    LeftAlt,
+    LeftSuper,
+    RightShift,
+    RightCtrl,
+    RightAlt,
+    RightSuper,
+}
+
+/// Returns the control character produced by Ctrl + `c`, if any.
+fn control_char(c: char) -> Option<u8> {
+    match c {
+        'a'...'z'             => Some(c as u8 - b'a' + 1),
+        'A'...'Z'             => Some(c as u8 - b'A' + 1),
+        '@' | ' ' | '2'       => Some(0x00),
+        '[' | '3'             => Some(0x1B),
+        '\\' | '4'            => Some(0x1C),
+        ']' | '5'             => Some(0x1D),
+        '^' | '~' | '6'       => Some(0x1E),
+        '_' | '-' | '/' | '7' => Some(0x1F),
+        '?' | '8'             => Some(0x7F),
+        // Already a control character
+        c if (c as u32) < 0x20 || c == '\x7F' => Some(c as u8),
+        _                     => None,
+    }
+}
+
+/// Writes ``ESC`` if alt is pressed, used for keys which send alt as a prefix.
+#[inline]
+fn alt_prefix<W: Write>(mods: Modifiers, out: &mut W) -> io::Result<()> {
+    if mods.contains(ALT) {
+        out.write_all(b"\x1B")
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes a cursor-style key, ``CSI c`` or ``CSI 1 ; m c`` if modifiers are present.
+#[inline]
+fn csi_letter<W: Write>(c: char, mods: Modifiers, out: &mut W) -> io::Result<()> {
+    if mods.is_empty() {
+        write!(out, "\x1B[{}", c)
+    } else {
+        write!(out, "\x1B[1;{}{}", mods.param(), c)
+    }
+}
+
+/// Writes an SS3-style key, ``SS3 c`` or ``CSI 1 ; m c`` if modifiers are present.
+#[inline]
+fn ss3_letter<W: Write>(c: char, mods: Modifiers, out: &mut W) -> io::Result<()> {
+    if mods.is_empty() {
+        write!(out, "\x1BO{}", c)
+    } else {
+        write!(out, "\x1B[1;{}{}", mods.param(), c)
+    }
+}
+
+/// Writes a cursor key, uses ``SS3`` instead of ``CSI`` in application cursor mode.
+#[inline]
+fn cursor_key<W: Write>(c: char, mods: Modifiers, mode: KeyboardMode, out: &mut W) -> io::Result<()> {
+    if mode.app_cursor {
+        ss3_letter(c, mods, out)
+    } else {
+        csi_letter(c, mods, out)
+    }
+}
+
+/// Returns the final character of the application keypad sequence for a keypad key.
+fn keypad_letter(c: char) -> Option<char> {
+    Some(match c {
+        '0'...'9' => (b'p' + (c as u8 - b'0')) as char,
+        '*'       => 'j',
+        '+'       => 'k',
+        ','       => 'l',
+        '-'       => 'm',
+        '.'       => 'n',
+        '/'       => 'o',
+        '='       => 'X',
+        _         => return None,
+    })
+}
+
+/// Writes an application keypad key, ``SS3 c`` or ``SS3 m c`` if modifiers are present.
+#[inline]
+fn keypad_key<W: Write>(c: char, mods: Modifiers, out: &mut W) -> io::Result<()> {
+    if mods.is_empty() {
+        write!(out, "\x1BO{}", c)
+    } else {
+        write!(out, "\x1BO{}{}", mods.param(), c)
+    }
+}
+
+/// Returns the character code to report with ``CSI 27 ; m ; code ~`` if the key is to be
+/// encoded using modifyOtherKeys at the given level.
+fn modify_other_key(key: Key, mods: Modifiers, level: u8) -> Option<u32> {
+    use self::Key::*;
+
+    if level == 0 {
+        return None;
+    }
+
+    match key {
+        Char(c) => if (mods - SHIFT).is_empty() {
+            // Shift is already included in the text
+            None
+        } else if level >= 2 {
+            Some(c as u32)
+        } else if mods.contains(CTRL) && (control_char(c).is_none() || mods.contains(SHIFT) && c.is_alphabetic()) {
+            // Ctrl chords which do not have a distinct control character
+            Some(c as u32)
+        } else {
+            None
+        },
+        Enter     if !mods.is_empty()                    => Some(13),
+        Tab       if !mods.is_empty() && mods != SHIFT   => Some(9),
+        Escape    if !mods.is_empty()                    => Some(27),
+        // Ctrl + Backspace already has a distinct code
+        Backspace if !mods.is_empty() && (level >= 2 || mods != CTRL) => Some(127),
+        _         => None,
+    }
+}
+
+/// Writes a tilde-style key, ``CSI n ~`` or ``CSI n ; m ~`` if modifiers are present.
+#[inline]
+fn csi_tilde<W: Write>(n: u32, mods: Modifiers, out: &mut W) -> io::Result<()> {
+    if mods.is_empty() {
+        write!(out, "\x1B[{}~", n)
+    } else {
+        write!(out, "\x1B[{};{}~", n, mods.param())
+    }
+}
+
+/// Encodes a key event with the given modifiers.
+///
+/// Uses the kitty keyboard protocol if any kitty flags are set, otherwise keys are encoded as
+/// xterm does and only presses and repeats produce output.
+pub fn encode<W: Write>(key: Key, mods: Modifiers, state: KeyState, mode: KeyboardMode, out: W) -> io::Result<()> {
+    if !mode.kitty.is_empty() {
+        encode_kitty(key, mods, state, mode, out)
+    } else if state == KeyState::Release {
+        Ok(())
+    } else {
+        encode_legacy(key, mods, mode, out)
+    }
+}
+
+/// Encodes a key press with the given modifiers as xterm does.
+///
+/// Alt is sent as an ``ESC`` prefix for keys producing text, other keys use the modifier
+/// parameter form, eg. ``CSI 1 ; 5 A`` for Ctrl + Up.
+fn encode_legacy<W: Write>(key: Key, mods: Modifiers, mode: KeyboardMode, mut out: W) -> io::Result<()> {
+    use self::Key::*;
+
+    if let Some(code) = modify_other_key(key, mods, mode.modify_other_keys) {
+        // CSI 27 ; m ; code ~
+        return write!(out, "\x1B[27;{};{}~", mods.param(), code);
+    }
+
+    match key {
+        Char(c) => {
+            try!(alt_prefix(mods, &mut out));
+
+            if mods.contains(CTRL) {
+                if let Some(b) = control_char(c) {
+                    return out.write_all(&[b]);
+                }
+            }
+
+            write!(out, "{}", c)
+        },
+        Enter     => {
+            try!(alt_prefix(mods, &mut out));
+
+            out.write_all(if mode.new_line { &b"\r\n"[..] } else { &b"\r"[..] })
+        },
+        Tab if mods.contains(SHIFT) => out.write_all(b"\x1B[Z"),
+        Tab       => {
+            try!(alt_prefix(mods, &mut out));
+
+            out.write_all(b"\t")
+        },
+        Backspace => {
+            try!(alt_prefix(mods, &mut out));
+
+            out.write_all(if mods.contains(CTRL) { b"\x08" } else { b"\x7F" })
+        },
+        Escape    => {
+            try!(alt_prefix(mods, &mut out));
+
+            out.write_all(b"\x1B")
+        },
+        Up        => cursor_key('A', mods, mode, &mut out),
+        Down      => cursor_key('B', mods, mode, &mut out),
+        Right     => cursor_key('C', mods, mode, &mut out),
+        Left      => cursor_key('D', mods, mode, &mut out),
+        Home      => cursor_key('H', mods, mode, &mut out),
+        End       => cursor_key('F', mods, mode, &mut out),
+        Insert    => csi_tilde(2, mods, &mut out),
+        Delete    => csi_tilde(3, mods, &mut out),
+        PageUp    => csi_tilde(5, mods, &mut out),
+        PageDown  => csi_tilde(6, mods, &mut out),
+        F(1)      => ss3_letter('P', mods, &mut out),
+        F(2)      => ss3_letter('Q', mods, &mut out),
+        F(3)      => ss3_letter('R', mods, &mut out),
+        F(4)      => ss3_letter('S', mods, &mut out),
+        F(5)      => csi_tilde(15, mods, &mut out),
+        F(6)      => csi_tilde(17, mods, &mut out),
+        F(7)      => csi_tilde(18, mods, &mut out),
+        F(8)      => csi_tilde(19, mods, &mut out),
+        F(9)      => csi_tilde(20, mods, &mut out),
+        F(10)     => csi_tilde(21, mods, &mut out),
+        F(11)     => csi_tilde(23, mods, &mut out),
+        F(12)     => csi_tilde(24, mods, &mut out),
+        F(n)      => {
+            info!("Unsupported function key: F{}", n);
+
+            Ok(())
+        },
+        KeypadEnter if mode.app_keypad => keypad_key('M', mods, &mut out),
+        KeypadEnter => encode_legacy(Enter, mods, mode, out),
+        Keypad(c)   => match keypad_letter(c) {
+            Some(l) if mode.app_keypad => keypad_key(l, mods, &mut out),
+            _                          => encode_legacy(Char(c), mods, mode, out),
+        },
+        // Modifier keys on their own do not produce anything
+        LeftShift | LeftCtrl | LeftAlt | LeftSuper | RightShift | RightCtrl | RightAlt | RightSuper => Ok(()),
+    }
+}
+
+/// Returns the character produced by the same key without shift on a US layout.
+fn unshifted(c: char) -> char {
+    match c {
+        'A'...'Z' => (c as u8 - b'A' + b'a') as char,
+        '!' => '1', '@' => '2', '#' => '3', '$' => '4', '%' => '5',
+        '^' => '6', '&' => '7', '*' => '8', '(' => '9', ')' => '0',
+        '_' => '-', '+' => '=', '{' => '[', '}' => ']', '|' => '\\',
+        ':' => ';', '"' => '\'', '~' => '`', '<' => ',', '>' => '.', '?' => '/',
+        c   => c,
+    }
+}
+
+/// Returns the kitty key code and the final character of the sequence for a key.
+fn kitty_key(key: Key) -> Option<(u32, char)> {
+    use self::Key::*;
+
+    Some(match key {
+        Char(c)     => (unshifted(c) as u32, 'u'),
+        Enter       => (13, 'u'),
+        Tab         => (9, 'u'),
+        Backspace   => (127, 'u'),
+        Escape      => (27, 'u'),
+        Up          => (1, 'A'),
+        Down        => (1, 'B'),
+        Right       => (1, 'C'),
+        Left        => (1, 'D'),
+        Home        => (1, 'H'),
+        End         => (1, 'F'),
+        Insert      => (2, '~'),
+        Delete      => (3, '~'),
+        PageUp      => (5, '~'),
+        PageDown    => (6, '~'),
+        F(1)        => (1, 'P'),
+        F(2)        => (1, 'Q'),
+        // F3 uses the tilde form since CSI R is ambiguous with cursor position reports
+        F(3)        => (13, '~'),
+        F(4)        => (1, 'S'),
+        F(5)        => (15, '~'),
+        F(6)        => (17, '~'),
+        F(7)        => (18, '~'),
+        F(8)        => (19, '~'),
+        F(9)        => (20, '~'),
+        F(10)       => (21, '~'),
+        F(11)       => (23, '~'),
+        F(12)       => (24, '~'),
+        F(_)        => return None,
+        Keypad(c)   => (match c {
+            '0'...'9' => 57399 + (c as u32 - '0' as u32),
+            '.'       => 57409,
+            '/'       => 57410,
+            '*'       => 57411,
+            '-'       => 57412,
+            '+'       => 57413,
+            '='       => 57415,
+            ','       => 57416,
+            _         => return None,
+        }, 'u'),
+        KeypadEnter => (57414, 'u'),
+        LeftShift   => (57441, 'u'),
+        LeftCtrl    => (57442, 'u'),
+        LeftAlt     => (57443, 'u'),
+        LeftSuper   => (57444, 'u'),
+        RightShift  => (57447, 'u'),
+        RightCtrl   => (57448, 'u'),
+        RightAlt    => (57449, 'u'),
+        RightSuper  => (57450, 'u'),
+    })
+}
+
+/// Encodes a key event using the kitty keyboard protocol,
+/// ``CSI code[:shifted] ; modifiers[:event] ; text u`` or ``CSI 1 ; modifiers[:event] c`` for
+/// legacy functional keys.
+fn encode_kitty<W: Write>(key: Key, mods: Modifiers, state: KeyState, mode: KeyboardMode, mut out: W) -> io::Result<()> {
+    use self::Key::*;
+
+    let flags  = mode.kitty;
+    let all    = flags.contains(REPORT_ALL);
+    let events = flags.contains(REPORT_EVENTS);
+
+    if state == KeyState::Release && !events {
+        return Ok(());
+    }
+
+    // Keys which still use the legacy encoding unless all keys are reported
+    let legacy = !all && match key {
+        Char(_)                   => (mods - SHIFT).is_empty(),
+        Enter | Tab | Backspace   => mods.is_empty(),
+        LeftShift | LeftCtrl | LeftAlt | LeftSuper |
+        RightShift | RightCtrl | RightAlt | RightSuper => return Ok(()),
+        _                         => false,
+    };
+
+    if legacy {
+        // Text is only produced on press and repeat, the legacy keys do not report release
+        return if state == KeyState::Release {
+            Ok(())
+        } else {
+            encode_legacy(key, mods, mode, out)
+        };
+    }
+
+    let (code, end) = match kitty_key(key) {
+        Some(k) => k,
+        None    => return Ok(()),
+    };
+
+    let text = match key {
+        Char(c) if flags.contains(REPORT_TEXT) && state != KeyState::Release
+            && !mods.intersects(CTRL | ALT | SUPER) => Some(c),
+        _   => None,
+    };
+    let event = match state {
+        KeyState::Press              => None,
+        KeyState::Repeat if events   => Some(2),
+        KeyState::Repeat             => None,
+        KeyState::Release            => Some(3),
+    };
+
+    try!(write!(out, "\x1B["));
+
+    if end == 'u' || code != 1 || !mods.is_empty() || event.is_some() {
+        try!(write!(out, "{}", code));
+    }
+
+    if let Char(c) = key {
+        if flags.contains(REPORT_ALTERNATES) && mods.contains(SHIFT) && unshifted(c) != c {
+            try!(write!(out, ":{}", c as u32));
+        }
+    }
+
+    if !mods.is_empty() || event.is_some() || text.is_some() {
+        try!(write!(out, ";{}", mods.param()));
+
+        if let Some(e) = event {
+            try!(write!(out, ":{}", e));
+        }
+    }
+
+    if let Some(c) = text {
+        try!(write!(out, ";{}", c as u32));
+    }
+
+    write!(out, "{}", end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, mods: Modifiers, mode: KeyboardMode) -> String {
+        let mut out = Vec::new();
+
+        encode(key, mods, KeyState::Press, mode, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    fn legacy(k: Key, mods: Modifiers) -> String {
+        key(k, mods, KeyboardMode::default())
+    }
+
+    #[test]
+    fn cursor_keys_with_modifiers() {
+        assert_eq!(legacy(Key::Up, NONE), "\x1B[A");
+        assert_eq!(legacy(Key::Up, CTRL), "\x1B[1;5A");
+        assert_eq!(legacy(Key::Down, SHIFT), "\x1B[1;2B");
+        assert_eq!(legacy(Key::Right, ALT), "\x1B[1;3C");
+        assert_eq!(legacy(Key::Left, CTRL | SHIFT), "\x1B[1;6D");
+        assert_eq!(legacy(Key::Home, CTRL | ALT | SHIFT), "\x1B[1;8H");
+    }
+
+    #[test]
+    fn alt_prefix() {
+        assert_eq!(legacy(Key::Char('a'), ALT), "\x1Ba");
+        assert_eq!(legacy(Key::Char('A'), ALT | SHIFT), "\x1BA");
+        assert_eq!(legacy(Key::Char('a'), ALT | CTRL), "\x1B\x01");
+        assert_eq!(legacy(Key::Enter, ALT), "\x1B\r");
+        assert_eq!(legacy(Key::Backspace, ALT), "\x1B\x7F");
+        assert_eq!(legacy(Key::Escape, ALT), "\x1B\x1B");
+    }
+
+    #[test]
+    fn control_chars() {
+        assert_eq!(legacy(Key::Char('c'), CTRL), "\x03");
+        assert_eq!(legacy(Key::Char(' '), CTRL), "\x00");
+        assert_eq!(legacy(Key::Char('['), CTRL), "\x1B");
+        assert_eq!(legacy(Key::Backspace, CTRL), "\x08");
+    }
+
+    #[test]
+    fn function_keys() {
+        let plain = ["\x1BOP", "\x1BOQ", "\x1BOR", "\x1BOS", "\x1B[15~", "\x1B[17~",
+                     "\x1B[18~", "\x1B[19~", "\x1B[20~", "\x1B[21~", "\x1B[23~", "\x1B[24~"];
+        let ctrl  = ["\x1B[1;5P", "\x1B[1;5Q", "\x1B[1;5R", "\x1B[1;5S", "\x1B[15;5~", "\x1B[17;5~",
+                     "\x1B[18;5~", "\x1B[19;5~", "\x1B[20;5~", "\x1B[21;5~", "\x1B[23;5~", "\x1B[24;5~"];
+
+        for n in 1..13 {
+            assert_eq!(legacy(Key::F(n), NONE), plain[n as usize - 1]);
+            assert_eq!(legacy(Key::F(n), CTRL), ctrl[n as usize - 1]);
+        }
+
+        assert_eq!(legacy(Key::F(1), SHIFT), "\x1B[1;2P");
+        assert_eq!(legacy(Key::F(5), ALT | SHIFT), "\x1B[15;4~");
+        assert_eq!(legacy(Key::F(13), NONE), "");
+    }
+
+    #[test]
+    fn editing_keys() {
+        assert_eq!(legacy(Key::Insert, NONE), "\x1B[2~");
+        assert_eq!(legacy(Key::Delete, NONE), "\x1B[3~");
+        assert_eq!(legacy(Key::PageUp, NONE), "\x1B[5~");
+        assert_eq!(legacy(Key::PageDown, NONE), "\x1B[6~");
+        assert_eq!(legacy(Key::Delete, CTRL), "\x1B[3;5~");
+        assert_eq!(legacy(Key::PageUp, SHIFT), "\x1B[5;2~");
+    }
+
+    #[test]
+    fn releases_are_not_sent() {
+        let mut out = Vec::new();
+
+        encode(Key::Char('a'), NONE, KeyState::Release, KeyboardMode::default(), &mut out).unwrap();
+
+        assert_eq!(out, b"");
+    }
+
+    #[test]
+    fn application_cursor_keys() {
+        let app = KeyboardMode { app_cursor: true, ..KeyboardMode::default() };
+
+        assert_eq!(key(Key::Up, NONE, app), "\x1BOA");
+        assert_eq!(key(Key::Down, NONE, app), "\x1BOB");
+        assert_eq!(key(Key::Right, NONE, app), "\x1BOC");
+        assert_eq!(key(Key::Left, NONE, app), "\x1BOD");
+        assert_eq!(key(Key::Home, NONE, app), "\x1BOH");
+        assert_eq!(key(Key::End, NONE, app), "\x1BOF");
+        // Modifiers always use the CSI form
+        assert_eq!(key(Key::Up, CTRL, app), "\x1B[1;5A");
+        // Not affected by DECCKM
+        assert_eq!(key(Key::PageUp, NONE, app), "\x1B[5~");
+    }
+
+    #[test]
+    fn application_keypad() {
+        let app = KeyboardMode { app_keypad: true, ..KeyboardMode::default() };
+
+        assert_eq!(key(Key::Keypad('0'), NONE, app), "\x1BOp");
+        assert_eq!(key(Key::Keypad('9'), NONE, app), "\x1BOy");
+        assert_eq!(key(Key::Keypad('+'), NONE, app), "\x1BOk");
+        assert_eq!(key(Key::Keypad('='), NONE, app), "\x1BOX");
+        assert_eq!(key(Key::Keypad('5'), SHIFT, app), "\x1BO2u");
+        assert_eq!(key(Key::KeypadEnter, NONE, app), "\x1BOM");
+    }
+
+    #[test]
+    fn numeric_keypad() {
+        assert_eq!(legacy(Key::Keypad('0'), NONE), "0");
+        assert_eq!(legacy(Key::Keypad('+'), NONE), "+");
+        assert_eq!(legacy(Key::KeypadEnter, NONE), "\r");
+    }
+
+    #[test]
+    fn modify_other_keys_level_2() {
+        let mode = KeyboardMode { modify_other_keys: 2, ..KeyboardMode::default() };
+
+        assert_eq!(key(Key::Char('a'), CTRL, mode), "\x1B[27;5;97~");
+        assert_eq!(key(Key::Char('a'), ALT, mode), "\x1B[27;3;97~");
+        assert_eq!(key(Key::Char('A'), CTRL | SHIFT, mode), "\x1B[27;6;65~");
+        assert_eq!(key(Key::Char('1'), CTRL, mode), "\x1B[27;5;49~");
+        assert_eq!(key(Key::Enter, CTRL, mode), "\x1B[27;5;13~");
+        assert_eq!(key(Key::Tab, CTRL, mode), "\x1B[27;5;9~");
+        assert_eq!(key(Key::Escape, SHIFT, mode), "\x1B[27;2;27~");
+        assert_eq!(key(Key::Backspace, CTRL, mode), "\x1B[27;5;127~");
+        // Shift is part of the text
+        assert_eq!(key(Key::Char('A'), SHIFT, mode), "A");
+        assert_eq!(key(Key::Char('a'), NONE, mode), "a");
+        assert_eq!(key(Key::Tab, SHIFT, mode), "\x1B[Z");
+        // Keys with their own modifier encoding are not affected
+        assert_eq!(key(Key::Up, CTRL, mode), "\x1B[1;5A");
+    }
+
+    #[test]
+    fn modify_other_keys_level_1() {
+        let mode = KeyboardMode { modify_other_keys: 1, ..KeyboardMode::default() };
+
+        // Distinct control characters are kept
+        assert_eq!(key(Key::Char('a'), CTRL, mode), "\x01");
+        assert_eq!(key(Key::Char('a'), ALT, mode), "\x1Ba");
+        assert_eq!(key(Key::Backspace, CTRL, mode), "\x08");
+        // Ambiguous chords
+        assert_eq!(key(Key::Char('A'), CTRL | SHIFT, mode), "\x1B[27;6;65~");
+        assert_eq!(key(Key::Char(';'), CTRL, mode), "\x1B[27;5;59~");
+        assert_eq!(key(Key::Enter, CTRL, mode), "\x1B[27;5;13~");
+    }
+
+    fn kitty(k: Key, mods: Modifiers, state: KeyState, flags: KittyFlags) -> String {
+        let mut out = Vec::new();
+
+        encode(k, mods, state, KeyboardMode { kitty: flags, ..KeyboardMode::default() }, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn kitty_disambiguate() {
+        let press = |k, mods| kitty(k, mods, KeyState::Press, DISAMBIGUATE);
+
+        // Text and unambiguous keys keep the legacy encoding
+        assert_eq!(press(Key::Char('a'), NONE), "a");
+        assert_eq!(press(Key::Char('A'), SHIFT), "A");
+        assert_eq!(press(Key::Enter, NONE), "\r");
+        assert_eq!(press(Key::Tab, NONE), "\t");
+        assert_eq!(press(Key::Backspace, NONE), "\x7F");
+        assert_eq!(press(Key::Up, NONE), "\x1B[A");
+        assert_eq!(press(Key::F(1), NONE), "\x1B[P");
+        assert_eq!(press(Key::F(5), NONE), "\x1B[15~");
+        // Chords, Escape and the keypad
+        assert_eq!(press(Key::Char('a'), CTRL), "\x1B[97;5u");
+        assert_eq!(press(Key::Char('a'), ALT), "\x1B[97;3u");
+        assert_eq!(press(Key::Char('A'), CTRL | SHIFT), "\x1B[97;6u");
+        assert_eq!(press(Key::Escape, NONE), "\x1B[27u");
+        assert_eq!(press(Key::Enter, CTRL), "\x1B[13;5u");
+        assert_eq!(press(Key::Up, CTRL), "\x1B[1;5A");
+        assert_eq!(press(Key::F(3), NONE), "\x1B[13~");
+        assert_eq!(press(Key::Keypad('1'), NONE), "\x1B[57400u");
+        assert_eq!(press(Key::KeypadEnter, NONE), "\x1B[57414u");
+        // Modifier keys and releases are not reported
+        assert_eq!(press(Key::LeftShift, NONE), "");
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Release, DISAMBIGUATE), "");
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Repeat, DISAMBIGUATE), "\x1B[97;5u");
+    }
+
+    #[test]
+    fn kitty_report_events() {
+        let flags = DISAMBIGUATE | REPORT_EVENTS;
+
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Press, flags), "\x1B[97;5u");
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Repeat, flags), "\x1B[97;5:2u");
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Release, flags), "\x1B[97;5:3u");
+        assert_eq!(kitty(Key::Up, NONE, KeyState::Press, flags), "\x1B[A");
+        assert_eq!(kitty(Key::Up, NONE, KeyState::Repeat, flags), "\x1B[1;1:2A");
+        assert_eq!(kitty(Key::Up, NONE, KeyState::Release, flags), "\x1B[1;1:3A");
+        assert_eq!(kitty(Key::Escape, NONE, KeyState::Release, flags), "\x1B[27;1:3u");
+        // Text keys use the legacy encoding, which has no release
+        assert_eq!(kitty(Key::Char('a'), NONE, KeyState::Repeat, flags), "a");
+        assert_eq!(kitty(Key::Char('a'), NONE, KeyState::Release, flags), "");
+    }
+
+    #[test]
+    fn kitty_report_all() {
+        let press = |k, mods| kitty(k, mods, KeyState::Press, REPORT_ALL);
+
+        assert_eq!(press(Key::Char('a'), NONE), "\x1B[97u");
+        assert_eq!(press(Key::Char('A'), SHIFT), "\x1B[97;2u");
+        assert_eq!(press(Key::Enter, NONE), "\x1B[13u");
+        assert_eq!(press(Key::Tab, NONE), "\x1B[9u");
+        assert_eq!(press(Key::Backspace, NONE), "\x1B[127u");
+        assert_eq!(press(Key::LeftShift, SHIFT), "\x1B[57441;2u");
+        assert_eq!(press(Key::RightCtrl, CTRL), "\x1B[57448;5u");
+        assert_eq!(press(Key::Up, NONE), "\x1B[A");
+
+        assert_eq!(kitty(Key::Char('a'), NONE, KeyState::Release, REPORT_ALL), "");
+        assert_eq!(kitty(Key::Char('a'), NONE, KeyState::Release, REPORT_ALL | REPORT_EVENTS), "\x1B[97;1:3u");
+        assert_eq!(kitty(Key::LeftAlt, NONE, KeyState::Release, REPORT_ALL | REPORT_EVENTS), "\x1B[57443;1:3u");
+    }
+
+    #[test]
+    fn kitty_report_text() {
+        let flags = REPORT_ALL | REPORT_TEXT;
+
+        assert_eq!(kitty(Key::Char('a'), NONE, KeyState::Press, flags), "\x1B[97;1;97u");
+        assert_eq!(kitty(Key::Char('A'), SHIFT, KeyState::Press, flags), "\x1B[97;2;65u");
+        // Chords produce no text
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Press, flags), "\x1B[97;5u");
+        assert_eq!(kitty(Key::Char('a'), NONE, KeyState::Release, flags | REPORT_EVENTS), "\x1B[97;1:3u");
+    }
+
+    #[test]
+    fn kitty_report_alternates() {
+        let flags = DISAMBIGUATE | REPORT_ALTERNATES;
+
+        assert_eq!(kitty(Key::Char('A'), CTRL | SHIFT, KeyState::Press, flags), "\x1B[97:65;6u");
+        assert_eq!(kitty(Key::Char('!'), ALT | SHIFT, KeyState::Press, flags), "\x1B[49:33;4u");
+        // No shifted key
+        assert_eq!(kitty(Key::Char('a'), CTRL, KeyState::Press, flags), "\x1B[97;5u");
+        assert_eq!(kitty(Key::Char('1'), CTRL | SHIFT, KeyState::Press, flags), "\x1B[49;6u");
+        // Not reported without the flag
+        assert_eq!(kitty(Key::Char('A'), CTRL | SHIFT, KeyState::Press, DISAMBIGUATE), "\x1B[97;6u");
+        // Legacy text is unaffected
+        assert_eq!(kitty(Key::Char('A'), SHIFT, KeyState::Press, flags), "A");
+    }
+}
@@ -5,12 +5,18 @@ extern crate chomp;
 #[macro_use]
 extern crate log;
 
+use std::cmp;
 use std::io;
 use std::io::Write;
 
+use chomp::buffer::{SliceStream, Stream, StreamError};
+
 pub mod ctrl;
+pub mod input;
+pub mod mouse;
 
 mod grid;
+mod terminfo;
 
 pub mod char_mode {
     bitflags!{
@@ -19,6 +25,7 @@ pub mod char_mode {
             const ITALIC     = 0b00000010,
             const INVERSE    = 0b00000100,
             const UNDERLINED = 0b00001000,
+            const BLINKING   = 0b00010000,
 
             const DEFAULT   = 0,
         }
@@ -64,12 +71,19 @@ bitflags!{
 
 
         /// If the cursor should blink
-        // TODO: Implement
         const BLINK       = 0b00000100,
         /// If the cursor should be visible
         const SHOW_CURSOR = 0b00001000,
         /// If we should send focus events
         const SEND_FOCUS  = 0b00010000,
+        /// If pasted text should be surrounded by ``ESC [ 200 ~`` and ``ESC [ 201 ~``
+        const BRACKETED_PASTE = 0b00100000,
+        /// If input sent to the application should also be displayed, send/receive mode (SRM)
+        /// reset
+        const LOCAL_ECHO      = 0b01000000,
+        /// If the application is in the middle of a synchronized update, the screen should not
+        /// be redrawn
+        const SYNC_UPDATE     = 0b10000000,
     }
 }
 
@@ -80,6 +94,38 @@ impl Default for Mode {
     }
 }
 
+/// The shape of the cursor.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CursorShape {
+    /// Fills the whole cell
+    Block,
+    /// Line at the bottom of the cell
+    Underline,
+    /// Vertical line at the left edge of the cell
+    Bar,
+}
+
+impl Default for CursorShape {
+    #[inline]
+    fn default() -> Self {
+        CursorShape::Block
+    }
+}
+
+/// Position and style of the cursor for rendering.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CursorInfo {
+    /// Column, 0-indexed from the left edge of the terminal.
+    pub col:     usize,
+    /// Row, 0-indexed from the top edge of the terminal.
+    pub row:     usize,
+    pub shape:   CursorShape,
+    /// If the cursor should blink
+    pub blink:   bool,
+    /// If the terminal has focus
+    pub focused: bool,
+}
+
 /// Describes the visual area which is visible of the terminal contents.
 pub trait Display {
     /// Iterates all glyphs used in the current displayed content and calls the provided
@@ -170,6 +216,83 @@ impl Display for Term {
 
 use grid::{Cursor, Grid, Movement};
 
+/// Maximum number of entries in the kitty keyboard protocol flag stack, the oldest entries are
+/// dropped when it is full.
+const KEYBOARD_STACK_SIZE: usize = 16;
+
+/// Terminal name reported to applications.
+const NAME: &'static str = "kopparoxid";
+
+/// Conformance level reported in the primary device attributes, VT220 since we implement a subset
+/// of its control functions and never send 8-bit controls.
+const CONFORMANCE_LEVEL: usize = 62;
+
+/// Optional features reported in the primary device attributes, the value is the attribute.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Feature {
+    Columns132          = 1,
+    Printer             = 2,
+    Sixel               = 4,
+    SelectiveErase      = 6,
+    UserDefinedKeys     = 8,
+    NationalCharsets    = 9,
+    TechnicalCharacters = 15,
+    HorizontalScrolling = 21,
+    AnsiColor           = 22,
+    RectangularEditing  = 28,
+    TextLocator         = 29,
+}
+
+const FEATURES: [Feature; 11] = [
+    Feature::Columns132,
+    Feature::Printer,
+    Feature::Sixel,
+    Feature::SelectiveErase,
+    Feature::UserDefinedKeys,
+    Feature::NationalCharsets,
+    Feature::TechnicalCharacters,
+    Feature::HorizontalScrolling,
+    Feature::AnsiColor,
+    Feature::RectangularEditing,
+    Feature::TextLocator,
+];
+
+/// Version of the terminal encoded as a single number for secondary device attributes,
+/// ``major * 10000 + minor * 100 + patch``.
+fn version_number() -> usize {
+    let major = env!("CARGO_PKG_VERSION_MAJOR").parse::<usize>().unwrap_or(0);
+    let minor = env!("CARGO_PKG_VERSION_MINOR").parse::<usize>().unwrap_or(0);
+    let patch = env!("CARGO_PKG_VERSION_PATCH").parse::<usize>().unwrap_or(0);
+
+    major * 10000 + minor * 100 + patch
+}
+
+/// Maps a mouse tracking private mode to the tracking it enables.
+fn mouse_tracking(m: ctrl::PrivateMode) -> mouse::Tracking {
+    use ctrl::PrivateMode::*;
+
+    match m {
+        MouseTrackingX10  => mouse::Tracking::X10,
+        MouseTrackingX11  => mouse::Tracking::Normal,
+        MouseTrackingCell => mouse::Tracking::ButtonEvent,
+        MouseTrackingAny  => mouse::Tracking::AnyEvent,
+        _                 => mouse::Tracking::Off,
+    }
+}
+
+/// Maps a mouse encoding private mode to the encoding it enables.
+fn mouse_encoding(m: ctrl::PrivateMode) -> mouse::Encoding {
+    use ctrl::PrivateMode::*;
+
+    match m {
+        MouseModeUtf8      => mouse::Encoding::Utf8,
+        MouseModeSGR       => mouse::Encoding::Sgr,
+        MouseModeUrxvt     => mouse::Encoding::Urxvt,
+        MouseModeSGRPixels => mouse::Encoding::SgrPixels,
+        _                  => mouse::Encoding::Default,
+    }
+}
+
 #[derive(Debug)]
 pub struct Term {
     /// Terminal cell grid
@@ -183,6 +306,20 @@ pub struct Term {
     title:  String,
     /// Terminal mode
     mode:   Mode,
+    /// Size of the terminal area in pixels (width, height)
+    pixels: (u32, u32),
+    /// Shape of the cursor, blinking is controlled by `BLINK`
+    cursor_shape: CursorShape,
+    /// Current phase of blinking cursor and text, true = visible
+    blink_visible: bool,
+    /// Modes affecting the encoding of keys
+    keyboard: input::KeyboardMode,
+    /// Kitty keyboard protocol flags saved by push, the current flags are in `keyboard`
+    keyboard_stack: Vec<input::KittyFlags>,
+    /// Mouse reporting mode
+    mouse:  mouse::Mouse,
+    /// If the terminal has focus, tracked even when focus events are not sent
+    focused: bool,
 }
 
 impl Term {
@@ -195,20 +332,53 @@ impl Term {
             title:   String::new(),
             style:   Style::default(),
             mode:    Mode::default(),
+            pixels:  (0, 0),
+            cursor_shape: CursorShape::default(),
+            blink_visible: true,
+            keyboard: input::KeyboardMode::default(),
+            keyboard_stack: Vec::new(),
+            mouse:   mouse::Mouse::default(),
+            focused: true,
         }
     }
 
-    /// Resizes to (width, height)
+    /// Sets the number of lines scrolled off the screen which are kept, 0 disables the scrollback.
+    #[inline]
+    pub fn set_scrollback_limit(&mut self, limit: usize) {
+        self.grid.set_scrollback_limit(limit);
+    }
+
+    /// Number of lines in the scrollback.
     #[inline]
-    pub fn resize(&mut self, size: (usize, usize)) {
+    pub fn scrollback_len(&self) -> usize {
+        self.grid.scrollback().len()
+    }
+
+    /// Resizes to (width, height) cells, with the terminal area being (width, height) pixels.
+    #[inline]
+    pub fn resize(&mut self, size: (usize, usize), pixels: (u32, u32)) {
         if size != self.grid.size() {
             self.grid.resize(size.0, size.1);
         }
 
+        self.pixels = pixels;
+
         // self.pos  = self.pos.limit_within(self.size);
         // TODO: Limit cursor to within, mainly for display purposes
     }
 
+    /// Size of a single cell in pixels (width, height), (0, 0) if the size is unknown.
+    #[inline]
+    pub fn cell_size(&self) -> (u32, u32) {
+        let (w, h) = self.grid.size();
+
+        if w == 0 || h == 0 {
+            (0, 0)
+        } else {
+            (self.pixels.0 / w as u32, self.pixels.1 / h as u32)
+        }
+    }
+
     #[inline]
     fn put_char(&mut self, c: usize) {
         self.grid.put(&mut self.cursor, (c, self.style))
@@ -246,10 +416,12 @@ impl Term {
                         Set(Italicized)   => self.style.attrs.insert(ITALIC),
                         Set(Inverse)      => self.style.attrs.insert(INVERSE),
                         Set(Underlined)   => self.style.attrs.insert(UNDERLINED),
+                        Set(Blink)        => self.style.attrs.insert(BLINKING),
                         Unset(Bold)       => self.style.attrs.remove(BOLD),
                         Unset(Italicized) => self.style.attrs.remove(ITALIC),
                         Unset(Inverse)    => self.style.attrs.remove(INVERSE),
                         Unset(Underlined) => self.style.attrs.remove(UNDERLINED),
+                        Unset(Blink)      => self.style.attrs.remove(BLINKING),
                         _                 => {
                             println!("Unknown char attr: {:?}", a);
                         },
@@ -287,45 +459,144 @@ impl Term {
             SendPrimaryDeviceAttributes => {
                 // CSI ? Pm c
                 // where Pm = int separated by ;
-                // we support an international terminal
-                // 1  132 columns
-                // 2  Printer port
-                // 4  Sixel
-                // 6  Selective erase
-                // 7  Soft character set (DRCS)
-                // 8  User-defined keys (UDKs)
-                // 9  National replacement character sets (NRCS) (International terminal only)
-                // 12 Yugoslavian (SCS)
-                // 15 Technical character set
-                // 18 Windowing capability
-                // 21 Horizontal scrolling
-                // 23 Greek
-                // 24 Turkish
-                // 42 ISO Latin-2 character set
-                // 44 PCTerm
-                // 45 Soft key map
-                // 46 ASCII emulation
-                return write!(out, "\x1B[?64;1;2;6;7;8;9;12;15;18;21;23;24;42;44;45;46c");
+                try!(write!(out, "\x1B[?"));
+
+                for (i, a) in self.device_attributes().iter().enumerate() {
+                    try!(write!(out, "{}{}", if i > 0 { ";" } else { "" }, a));
+                }
+
+                return write!(out, "c");
             },
             SendSecondaryDeviceAttributes => {
-                // we pretend to be a VT525 here, version 2.0
-                return write!(out, "\x1B[>65;20;1c");
+                // CSI > Pp ; Pv ; Pc c
+                // Pp = 1: VT220, Pv = firmware version, Pc = 0: no ROM cartridge
+                return write!(out, "\x1B[>1;{};0c", version_number());
+            },
+            SendTertiaryDeviceAttributes => {
+                // DCS ! | D...D ST
+                // Unit id is "Cu2O" hex-encoded
+                return write!(out, "\x1BP!|4375324F\x1B\\");
+            },
+            RequestTerminalVersion => {
+                // DCS > | text ST
+                return write!(out, "\x1BP>|{}({})\x1B\\", NAME, env!("CARGO_PKG_VERSION"));
+            },
+            SetCursorStyle(style) => {
+                use ctrl::CursorStyle::*;
+
+                let (shape, blink) = match style {
+                    BlinkingBlock     => (CursorShape::Block,     true),
+                    SteadyBlock       => (CursorShape::Block,     false),
+                    BlinkingUnderline => (CursorShape::Underline, true),
+                    SteadyUnderline   => (CursorShape::Underline, false),
+                    BlinkingBar       => (CursorShape::Bar,       true),
+                    SteadyBar         => (CursorShape::Bar,       false),
+                };
+
+                self.cursor_shape = shape;
+
+                if blink {
+                    self.mode.insert(BLINK);
+                } else {
+                    self.mode.remove(BLINK);
+                }
+            },
+            WindowReport(r) => {
+                use ctrl::WindowReport::*;
+
+                let (w, h) = self.grid.size();
+
+                // CSI Ps ; height ; width t
+                return match r {
+                    TextAreaPixels => write!(out, "\x1B[4;{};{}t", self.pixels.1, self.pixels.0),
+                    CellPixels     => {
+                        let cell = self.cell_size();
+
+                        write!(out, "\x1B[6;{};{}t", cell.1, cell.0)
+                    },
+                    TextAreaCells  => write!(out, "\x1B[8;{};{}t", h, w),
+                    // We do not know anything about the screen, report the text area
+                    ScreenCells    => write!(out, "\x1B[9;{};{}t", h, w),
+                };
+            },
+            RequestPrivateMode(n) => {
+                // CSI ? Ps ; Pm $ y
+                let status = ctrl::private_mode(n).map(|m| self.private_mode_status(m)).unwrap_or(0);
+
+                return write!(out, "\x1B[?{};{}$y", n, status);
+            },
+            RequestStatusString(req) => return self.report_status_string(&req, out),
+            RequestTermcap(names) => {
+                for name in names {
+                    try!(self.report_termcap(&name, &mut out));
+                }
+            },
+            SetKeypadMode(m) => self.keyboard.app_keypad = m == ctrl::KeypadMode::Application,
+            PushKeyboardFlags(flags) => {
+                if self.keyboard_stack.len() >= KEYBOARD_STACK_SIZE {
+                    self.keyboard_stack.remove(0);
+                }
+
+                self.keyboard_stack.push(self.keyboard.kitty);
+
+                self.keyboard.kitty = input::KittyFlags::from_bits_truncate(flags);
+            },
+            PopKeyboardFlags(n) => {
+                for _ in 0..n {
+                    match self.keyboard_stack.pop() {
+                        Some(flags) => self.keyboard.kitty = flags,
+                        None        => {
+                            // Popping everything resets all flags
+                            self.keyboard.kitty = input::KittyFlags::empty();
+
+                            break;
+                        },
+                    }
+                }
+            },
+            SetModifyOtherKeys(n) => self.keyboard.modify_other_keys = cmp::min(n, 2) as u8,
+            QueryModifyOtherKeys => {
+                // CSI > 4 ; Ps m
+                return write!(out, "\x1B[>4;{}m", self.keyboard.modify_other_keys);
+            },
+            QueryKeyboardFlags => {
+                // CSI ? flags u
+                return write!(out, "\x1B[?{}u", self.keyboard.kitty.bits());
+            },
+            SetKeyboardFlags(flags, m) => {
+                use ctrl::KeyboardFlagsMode::*;
+
+                let flags = input::KittyFlags::from_bits_truncate(flags);
+
+                match m {
+                    Set        => self.keyboard.kitty = flags,
+                    Union      => self.keyboard.kitty.insert(flags),
+                    Difference => self.keyboard.kitty.remove(flags),
+                }
             },
             CursorPositionReport => {
                 // CSI [ line ; col R
                 return write!(out, "\x1B[{};{}R", self.cursor.row() + 1, self.cursor.col() + 1);
             },
             ModeSet(modes) => {
+                use ctrl::Mode::*;
+
                 for m in modes {
                     match m {
-                        _ => error!("Unknown mode (set): {:?}", m),
+                        AutomaticNewline => self.mode.insert(NEW_LINE),
+                        SendReceive      => self.mode.remove(LOCAL_ECHO),
+                        _                => error!("Unknown mode (set): {:?}", m),
                     }
                 }
             },
             ModeReset(modes) => {
+                use ctrl::Mode::*;
+
                 for m in modes {
                     match m {
-                        _ => error!("Unknown mode (reset): {:?}", m),
+                        AutomaticNewline => self.mode.remove(NEW_LINE),
+                        SendReceive      => self.mode.insert(LOCAL_ECHO),
+                        _                => error!("Unknown mode (reset): {:?}", m),
                     }
                 }
             },
@@ -334,10 +605,27 @@ impl Term {
 
                 for m in modes {
                     match m {
-                        ShowCursor      => self.mode.insert(SHOW_CURSOR),
-                        CursorBlink     => self.mode.insert(BLINK),
-                        SendFocusEvents => self.mode.insert(SEND_FOCUS),
-                        _               => error!("Unknown private mode (set): {:?}", m),
+                        ApplicationCursorKeys => self.keyboard.app_cursor = true,
+                        ShowCursor            => self.mode.insert(SHOW_CURSOR),
+                        CursorBlink           => self.mode.insert(BLINK),
+                        SendFocusEvents       => if !self.mode.contains(SEND_FOCUS) {
+                            self.mode.insert(SEND_FOCUS);
+
+                            // Report the current state immediately like xterm
+                            try!(self.write_focus(&mut out));
+                        },
+                        BracketedPaste        => self.mode.insert(BRACKETED_PASTE),
+                        SynchronizedOutput    => self.mode.insert(SYNC_UPDATE),
+                        Autowrap              => self.cursor.set_autowrap(true),
+                        MouseTrackingX10      |
+                        MouseTrackingX11      |
+                        MouseTrackingCell     |
+                        MouseTrackingAny      => self.mouse.set_tracking(mouse_tracking(m)),
+                        MouseModeUtf8         |
+                        MouseModeSGR          |
+                        MouseModeUrxvt        |
+                        MouseModeSGRPixels    => self.mouse.encoding = mouse_encoding(m),
+                        _                     => error!("Unknown private mode (set): {:?}", m),
                     }
                 }
             },
@@ -346,13 +634,32 @@ impl Term {
 
                 for m in modes {
                     match m {
-                        ShowCursor      => self.mode.remove(SHOW_CURSOR),
-                        CursorBlink     => self.mode.remove(BLINK),
-                        SendFocusEvents => self.mode.remove(SEND_FOCUS),
-                        _               => error!("Unknown private mode (reset): {:?}", m),
+                        ApplicationCursorKeys => self.keyboard.app_cursor = false,
+                        ShowCursor            => self.mode.remove(SHOW_CURSOR),
+                        CursorBlink           => self.mode.remove(BLINK),
+                        SendFocusEvents       => self.mode.remove(SEND_FOCUS),
+                        BracketedPaste        => self.mode.remove(BRACKETED_PASTE),
+                        SynchronizedOutput    => self.mode.remove(SYNC_UPDATE),
+                        Autowrap              => self.cursor.set_autowrap(false),
+                        MouseTrackingX10      |
+                        MouseTrackingX11      |
+                        MouseTrackingCell     |
+                        MouseTrackingAny      => if self.mouse.tracking == mouse_tracking(m) {
+                            self.mouse.set_tracking(mouse::Tracking::Off);
+                        },
+                        MouseModeUtf8         |
+                        MouseModeSGR          |
+                        MouseModeUrxvt        |
+                        MouseModeSGRPixels    => if self.mouse.encoding == mouse_encoding(m) {
+                            self.mouse.encoding = mouse::Encoding::Default;
+                        },
+                        _                     => error!("Unknown private mode (reset): {:?}", m),
                     }
                 }
             },
+            DeviceControlString(data) => {
+                error!("Unknown device control string: {:?}", String::from_utf8_lossy(&data));
+            },
             _                                           => {
                 error!("Unknown seq: {:?}", item);
             },
@@ -361,6 +668,277 @@ impl Term {
         Ok(())
     }
 
+    /// If an optional feature is implemented.
+    fn supports(&self, f: Feature) -> bool {
+        use self::Feature::*;
+
+        match f {
+            // SGR 30-37, 40-47 and the 256 color and RGB extensions
+            AnsiColor           => true,
+            // DECCOLM, DECSCA, NRCS designation, SL/SR and DECCRA/DECFRA are ignored
+            Columns132          |
+            SelectiveErase      |
+            NationalCharsets    |
+            HorizontalScrolling |
+            RectangularEditing  => false,
+            // No implementation at all
+            Printer             |
+            Sixel               |
+            UserDefinedKeys     |
+            TechnicalCharacters |
+            TextLocator         => false,
+        }
+    }
+
+    /// Returns the primary device attributes, the conformance level followed by the implemented
+    /// features.
+    fn device_attributes(&self) -> Vec<usize> {
+        Some(CONFORMANCE_LEVEL).into_iter()
+            .chain(FEATURES.iter().filter(|&&f| self.supports(f)).map(|&f| f as usize))
+            .collect()
+    }
+
+    /// Returns the DECRQM status value for the given private mode, 1 = set, 2 = reset,
+    /// 3 = permanently set and 4 = permanently reset.
+    fn private_mode_status(&self, m: ctrl::PrivateMode) -> usize {
+        use ctrl::PrivateMode::*;
+
+        fn status(set: bool) -> usize {
+            if set { 1 } else { 2 }
+        }
+
+        match m {
+            ApplicationCursorKeys => status(self.keyboard.app_cursor),
+            ShowCursor            => status(self.mode.contains(SHOW_CURSOR)),
+            CursorBlink           => status(self.mode.contains(BLINK)),
+            SendFocusEvents       => status(self.mode.contains(SEND_FOCUS)),
+            BracketedPaste        => status(self.mode.contains(BRACKETED_PASTE)),
+            SynchronizedOutput    => status(self.mode.contains(SYNC_UPDATE)),
+            Autowrap              => status(self.cursor.autowrap()),
+            MouseTrackingX10      |
+            MouseTrackingX11      |
+            MouseTrackingCell     |
+            MouseTrackingAny      => status(self.mouse.tracking == mouse_tracking(m)),
+            MouseModeUtf8         |
+            MouseModeSGR          |
+            MouseModeUrxvt        |
+            MouseModeSGRPixels    => status(self.mouse.encoding == mouse_encoding(m)),
+            // Key repeat is handled by the windowing system
+            Autorepeat            => 3,
+            // Not implemented
+            _                     => 4,
+        }
+    }
+
+    /// Writes the current character attributes as SGR parameters, without the final ``m``.
+    fn write_char_attrs<W: Write>(&self, mut out: W) -> io::Result<()> {
+        use self::char_mode::*;
+
+        fn color<W: Write>(mut out: W, c: ctrl::Color, base: u8) -> io::Result<()> {
+            use ctrl::Color::*;
+
+            match c {
+                Black        => write!(out, ";{}", base),
+                Red          => write!(out, ";{}", base + 1),
+                Green        => write!(out, ";{}", base + 2),
+                Yellow       => write!(out, ";{}", base + 3),
+                Blue         => write!(out, ";{}", base + 4),
+                Magenta      => write!(out, ";{}", base + 5),
+                Cyan         => write!(out, ";{}", base + 6),
+                White        => write!(out, ";{}", base + 7),
+                Default      => Ok(()),
+                Palette(p)   => write!(out, ";{};5;{}", base + 8, p),
+                RGB(r, g, b) => write!(out, ";{};2;{};{};{}", base + 8, r, g, b),
+            }
+        }
+
+        try!(write!(out, "0"));
+
+        for &(flag, n) in &[(BOLD, 1), (ITALIC, 3), (UNDERLINED, 4), (BLINKING, 5), (INVERSE, 7)] {
+            if self.style.attrs.contains(flag) {
+                try!(write!(out, ";{}", n));
+            }
+        }
+
+        try!(color(&mut out, self.style.fg, 30));
+
+        color(&mut out, self.style.bg, 40)
+    }
+
+    /// Responds to a DECRQSS request.
+    fn report_status_string<W: Write>(&self, req: &[u8], mut out: W) -> io::Result<()> {
+        if req == b"m" {
+            // SGR
+            try!(write!(out, "\x1BP1$r"));
+            try!(self.write_char_attrs(&mut out));
+
+            write!(out, "m\x1B\\")
+        } else if req == b"r" {
+            // DECSTBM
+            let (top, bot) = self.grid.scroll_region();
+
+            write!(out, "\x1BP1$r{};{}r\x1B\\", top + 1, bot)
+        } else if req == b" q" {
+            // DECSCUSR
+            let style = match self.cursor_shape {
+                CursorShape::Block     => 1,
+                CursorShape::Underline => 3,
+                CursorShape::Bar       => 5,
+            } + if self.mode.contains(BLINK) { 0 } else { 1 };
+
+            write!(out, "\x1BP1$r{} q\x1B\\", style)
+        } else {
+            info!("Unknown DECRQSS request: {:?}", String::from_utf8_lossy(req));
+
+            write!(out, "\x1BP0$r\x1B\\")
+        }
+    }
+
+    /// Responds to a single XTGETTCAP capability request, name and value are hex-encoded.
+    fn report_termcap<W: Write>(&self, name: &str, mut out: W) -> io::Result<()> {
+        fn hex<W: Write>(mut out: W, s: &str) -> io::Result<()> {
+            for b in s.bytes() {
+                try!(write!(out, "{:02X}", b));
+            }
+
+            Ok(())
+        }
+
+        match terminfo::lookup(name) {
+            Some(value) => {
+                try!(write!(out, "\x1BP1+r"));
+                try!(hex(&mut out, name));
+
+                if let Some(v) = value {
+                    try!(write!(out, "="));
+                    try!(hex(&mut out, v));
+                }
+            },
+            None => {
+                try!(write!(out, "\x1BP0+r"));
+                try!(hex(&mut out, name));
+            },
+        }
+
+        write!(out, "\x1B\\")
+    }
+
+    /// If blinking cursor and text is currently in the visible phase.
+    #[inline]
+    pub fn blink_visible(&self) -> bool {
+        self.blink_visible
+    }
+
+    /// Toggles the blink phase.
+    #[inline]
+    pub fn toggle_blink(&mut self) {
+        self.blink_visible = !self.blink_visible;
+    }
+
+    /// Resets the blink phase to visible, returns true if it was not visible.
+    #[inline]
+    pub fn reset_blink(&mut self) -> bool {
+        let changed = !self.blink_visible;
+
+        self.blink_visible = true;
+
+        changed
+    }
+
+    /// Returns true if there is a visible blinking cursor or any blinking text on screen.
+    pub fn has_blinking(&self) -> bool {
+        use self::char_mode::BLINKING;
+
+        // Unfocused cursors do not blink
+        self.mode.contains(SHOW_CURSOR | BLINK) && self.focused
+            || self.grid.cells().any(|c| c.0 != 0 && c.1.attrs.contains(BLINKING))
+    }
+
+    /// Encodes a key event according to the current keyboard modes and writes the resulting
+    /// bytes to `out`.
+    #[inline]
+    pub fn encode_key<W: Write>(&self, key: input::Key, mods: input::Modifiers, state: input::KeyState, out: W) -> io::Result<()> {
+        let mode = input::KeyboardMode {
+            new_line: self.mode.contains(NEW_LINE),
+            ..self.keyboard
+        };
+
+        input::encode(key, mods, state, mode, out)
+    }
+
+    /// Displays data sent to the application if local echo is enabled (SRM reset), returns true
+    /// if anything was displayed.
+    pub fn local_echo(&mut self, data: &[u8]) -> bool {
+        if !self.mode.contains(LOCAL_ECHO) || data.is_empty() {
+            return false;
+        }
+
+        let mut stream = SliceStream::new(data);
+
+        loop {
+            match stream.parse(ctrl::parser) {
+                // Responses to echoed requests are not sent anywhere
+                Ok(s)                              => self.handle(s, io::sink()).unwrap(),
+                Err(StreamError::ParseError(b, e)) => {
+                    error!("{:?} at {:?} in local echo", e, String::from_utf8_lossy(b));
+                },
+                Err(_)                             => break,
+            }
+        }
+
+        true
+    }
+
+    /// Encodes a mouse event according to the current mouse reporting mode and writes the
+    /// resulting bytes to `out`, nothing is written if the event should not be reported.
+    #[inline]
+    pub fn encode_mouse<W: Write>(&mut self, event: mouse::Event, out: W) -> io::Result<()> {
+        self.mouse.encode(event, out)
+    }
+
+    /// Which mouse events are reported to the application.
+    #[inline]
+    pub fn mouse_tracking(&self) -> mouse::Tracking {
+        self.mouse.tracking
+    }
+
+    /// Writes pasted text to `out`, surrounded by ``ESC [ 200 ~`` and ``ESC [ 201 ~`` if
+    /// bracketed paste is enabled.
+    ///
+    /// Control characters except tab and newlines are removed so the text cannot end the paste
+    /// early or otherwise control the application, newlines are sent as carriage returns like
+    /// the Return key.
+    pub fn encode_paste<W: Write>(&self, text: &str, mut out: W) -> io::Result<()> {
+        let bracketed = self.mode.contains(BRACKETED_PASTE);
+
+        if bracketed {
+            try!(out.write_all(b"\x1B[200~"));
+        }
+
+        let mut prev = '\0';
+
+        for c in text.chars() {
+            match c {
+                // CRLF is sent as a single CR
+                '\n' if prev == '\r' => {},
+                '\n'                 => try!(out.write_all(b"\r")),
+                '\t' | '\r'          => try!(write!(out, "{}", c)),
+                // C0, DEL and C1
+                '\x00'...'\x1F'      |
+                '\x7F'...'\u{9F}'    => {},
+                c                    => try!(write!(out, "{}", c)),
+            }
+
+            prev = c;
+        }
+
+        if bracketed {
+            try!(out.write_all(b"\x1B[201~"));
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn get_title(&self) -> &str {
         &self.title
@@ -371,13 +949,400 @@ impl Term {
         self.mode.contains(SEND_FOCUS)
     }
 
-    /// Cursor position in (column, row)
+    /// If the application is in the middle of a synchronized update, the terminal should not
+    /// be rendered until it is done.
+    #[inline]
+    pub fn synchronized_update(&self) -> bool {
+        self.mode.contains(SYNC_UPDATE)
+    }
+
+    #[inline]
+    pub fn focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Sets if the terminal has focus, writes a focus event to `out` if focus events are enabled
+    /// and the focus changed.
+    pub fn set_focus<W: Write>(&mut self, focused: bool, out: W) -> io::Result<()> {
+        let changed  = self.focused != focused;
+
+        self.focused = focused;
+
+        if changed && self.mode.contains(SEND_FOCUS) {
+            self.write_focus(out)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes ``CSI I`` if focused, ``CSI O`` otherwise.
     #[inline]
-    pub fn get_cursor(&self) -> Option<(usize, usize)> {
+    fn write_focus<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(if self.focused { b"\x1B[I" } else { b"\x1B[O" })
+    }
+
+    /// Cursor position and style, `None` if the cursor is hidden.
+    #[inline]
+    pub fn get_cursor(&self) -> Option<CursorInfo> {
         if self.mode.contains(SHOW_CURSOR) {
-            Some((self.cursor.col(), self.cursor.row()))
+            Some(CursorInfo {
+                col:     self.cursor.col(),
+                row:     self.cursor.row(),
+                shape:   self.cursor_shape,
+                blink:   self.mode.contains(BLINK),
+                focused: self.focused,
+            })
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chomp::buffer::{SliceStream, Stream};
+
+    use super::{ctrl, input, version_number, Term, FEATURES, KEYBOARD_STACK_SIZE};
+
+    /// Lets the terminal handle `data` from the application, returns the responses.
+    fn reply(t: &mut Term, data: &[u8]) -> Vec<u8> {
+        let mut out    = Vec::new();
+        let mut stream = SliceStream::new(data);
+
+        while let Ok(s) = stream.parse(ctrl::parser) {
+            t.handle(s, &mut out).unwrap();
+        }
+
+        out
+    }
+
+    fn hex(s: &str) -> String {
+        s.bytes().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    #[test]
+    fn xtgettcap_round_trip() {
+        let mut t = Term::new_with_size(80, 24);
+        let req   = format!("\x1BP+q{};{}\x1B\\", hex("colors"), hex("am"));
+
+        assert_eq!(String::from_utf8(reply(&mut t, req.as_bytes())).unwrap(),
+                   format!("\x1BP1+r{}={}\x1B\\\x1BP1+r{}\x1B\\", hex("colors"), hex("256"), hex("am")));
+    }
+
+    #[test]
+    fn xtgettcap_unknown() {
+        let mut t = Term::new_with_size(80, 24);
+        let req   = format!("\x1BP+q{}\x1B\\", hex("foo"));
+
+        assert_eq!(String::from_utf8(reply(&mut t, req.as_bytes())).unwrap(), format!("\x1BP0+r{}\x1B\\", hex("foo")));
+    }
+
+    #[test]
+    fn decrqss() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[1;31m\x1BP$qm\x1B\\"), b"\x1BP1$r0;1;31m\x1B\\");
+        assert_eq!(reply(&mut t, b"\x1B[2;10r\x1BP$qr\x1B\\"), b"\x1BP1$r2;10r\x1B\\");
+        assert_eq!(reply(&mut t, b"\x1B[6 q\x1BP$q q\x1B\\"), b"\x1BP1$r6 q\x1B\\");
+        assert_eq!(reply(&mut t, b"\x1BP$qx\x1B\\"), b"\x1BP0$r\x1B\\");
+    }
+
+    #[test]
+    fn decrqm() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[?2004$p"), b"\x1B[?2004;2$y");
+        assert_eq!(reply(&mut t, b"\x1B[?2004h\x1B[?2004$p"), b"\x1B[?2004;1$y");
+        assert_eq!(reply(&mut t, b"\x1B[?1h\x1B[?1$p"), b"\x1B[?1;1$y");
+        assert_eq!(reply(&mut t, b"\x1B[?1002h\x1B[?1002$p\x1B[?1000$p"), b"\x1B[?1002;1$y\x1B[?1000;2$y");
+        // Permanently set and not implemented
+        assert_eq!(reply(&mut t, b"\x1B[?8$p"), b"\x1B[?8;3$y");
+        assert_eq!(reply(&mut t, b"\x1B[?5$p"), b"\x1B[?5;4$y");
+        // Unknown
+        assert_eq!(reply(&mut t, b"\x1B[?1234$p"), b"\x1B[?1234;0$y");
+    }
+
+    #[test]
+    fn primary_device_attributes() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[c"), b"\x1B[?62;22c");
+        assert_eq!(reply(&mut t, b"\x1B[0c"), b"\x1B[?62;22c");
+    }
+
+    #[test]
+    fn device_attributes_list_supported_features() {
+        let t     = Term::new_with_size(80, 24);
+        let attrs = t.device_attributes();
+
+        assert_eq!(attrs[0], 62);
+
+        for &f in FEATURES.iter() {
+            assert_eq!(attrs.contains(&(f as usize)), t.supports(f), "{:?}", f);
+        }
+    }
+
+    #[test]
+    fn secondary_device_attributes() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(String::from_utf8(reply(&mut t, b"\x1B[>c")).unwrap(), format!("\x1B[>1;{};0c", version_number()));
+    }
+
+    #[test]
+    fn tertiary_device_attributes() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[=c"), b"\x1BP!|4375324F\x1B\\");
+    }
+
+    #[test]
+    fn xtversion() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(String::from_utf8(reply(&mut t, b"\x1B[>q")).unwrap(), format!("\x1BP>|kopparoxid({})\x1B\\", env!("CARGO_PKG_VERSION")));
+        assert_eq!(String::from_utf8(reply(&mut t, b"\x1B[>0q")).unwrap(), format!("\x1BP>|kopparoxid({})\x1B\\", env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn window_reports() {
+        let mut t = Term::new_with_size(80, 24);
+
+        t.resize((80, 24), (800, 480));
+
+        assert_eq!(reply(&mut t, b"\x1B[14t"), b"\x1B[4;480;800t");
+        assert_eq!(reply(&mut t, b"\x1B[16t"), b"\x1B[6;20;10t");
+        assert_eq!(reply(&mut t, b"\x1B[18t"), b"\x1B[8;24;80t");
+        assert_eq!(reply(&mut t, b"\x1B[19t"), b"\x1B[9;24;80t");
+
+        t.resize((120, 40), (1210, 815));
+
+        assert_eq!(t.cell_size(), (10, 20));
+        assert_eq!(reply(&mut t, b"\x1B[14t"), b"\x1B[4;815;1210t");
+        assert_eq!(reply(&mut t, b"\x1B[16t"), b"\x1B[6;20;10t");
+        assert_eq!(reply(&mut t, b"\x1B[18t"), b"\x1B[8;40;120t");
+        assert_eq!(reply(&mut t, b"\x1B[19t"), b"\x1B[9;40;120t");
+    }
+
+    #[test]
+    fn window_reports_without_pixels() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(t.cell_size(), (0, 0));
+        assert_eq!(reply(&mut t, b"\x1B[14t"), b"\x1B[4;0;0t");
+        assert_eq!(reply(&mut t, b"\x1B[16t"), b"\x1B[6;0;0t");
+        assert_eq!(reply(&mut t, b"\x1B[18t"), b"\x1B[8;24;80t");
+
+        // No cells to divide the pixels by
+        let mut t = Term::new_with_size(0, 0);
+
+        t.resize((0, 0), (800, 480));
+
+        assert_eq!(t.cell_size(), (0, 0));
+        assert_eq!(reply(&mut t, b"\x1B[16t"), b"\x1B[6;0;0t");
+    }
+
+    #[test]
+    fn keyboard_flags_push_pop() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[?u"), b"\x1B[?0u");
+        assert_eq!(reply(&mut t, b"\x1B[>1u\x1B[?u"), b"\x1B[?1u");
+        assert_eq!(reply(&mut t, b"\x1B[>31u\x1B[?u"), b"\x1B[?31u");
+        // Unknown flags are dropped
+        assert_eq!(reply(&mut t, b"\x1B[>255u\x1B[?u"), b"\x1B[?31u");
+        assert_eq!(reply(&mut t, b"\x1B[<u\x1B[?u"), b"\x1B[?31u");
+        assert_eq!(reply(&mut t, b"\x1B[<u\x1B[?u"), b"\x1B[?1u");
+        assert_eq!(reply(&mut t, b"\x1B[>u\x1B[?u"), b"\x1B[?0u");
+        assert_eq!(reply(&mut t, b"\x1B[<2u\x1B[?u"), b"\x1B[?0u");
+    }
+
+    #[test]
+    fn keyboard_flags_pop_past_empty() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[<u\x1B[?u"), b"\x1B[?0u");
+        assert_eq!(reply(&mut t, b"\x1B[>3u\x1B[<5u\x1B[?u"), b"\x1B[?0u");
+        // Popping everything resets flags which were set without a push
+        assert_eq!(reply(&mut t, b"\x1B[=3u\x1B[<u\x1B[?u"), b"\x1B[?0u");
+    }
+
+    #[test]
+    fn keyboard_flags_stack_overflow() {
+        let mut t = Term::new_with_size(80, 24);
+
+        for flags in 1..(KEYBOARD_STACK_SIZE + 2) {
+            reply(&mut t, format!("\x1B[>{}u", flags).as_bytes());
+        }
+
+        assert_eq!(String::from_utf8(reply(&mut t, b"\x1B[?u")).unwrap(), format!("\x1B[?{}u", KEYBOARD_STACK_SIZE + 1));
+
+        // The oldest entry, the initial flags, has been dropped
+        let pop = format!("\x1B[<{}u\x1B[?u", KEYBOARD_STACK_SIZE);
+
+        assert_eq!(reply(&mut t, pop.as_bytes()), b"\x1B[?1u");
+        assert_eq!(reply(&mut t, b"\x1B[<u\x1B[?u"), b"\x1B[?0u");
+    }
+
+    #[test]
+    fn keyboard_flags_set() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert_eq!(reply(&mut t, b"\x1B[=3u\x1B[?u"), b"\x1B[?3u");
+        assert_eq!(reply(&mut t, b"\x1B[=8;2u\x1B[?u"), b"\x1B[?11u");
+        assert_eq!(reply(&mut t, b"\x1B[=1;3u\x1B[?u"), b"\x1B[?10u");
+        assert_eq!(reply(&mut t, b"\x1B[=5;1u\x1B[?u"), b"\x1B[?5u");
+        assert_eq!(reply(&mut t, b"\x1B[=u\x1B[?u"), b"\x1B[?0u");
+        // Setting changes the current flags, not the stack
+        assert_eq!(reply(&mut t, b"\x1B[>1u\x1B[=8u\x1B[<u\x1B[?u"), b"\x1B[?0u");
+    }
+
+    #[test]
+    fn keyboard_flags_select_encoding() {
+        let mut t   = Term::new_with_size(80, 24);
+        let escape  = |t: &Term| {
+            let mut out = Vec::new();
+
+            t.encode_key(input::Key::Escape, input::NONE, input::KeyState::Press, &mut out).unwrap();
+
+            out
+        };
+
+        assert_eq!(escape(&t), b"\x1B");
+
+        reply(&mut t, b"\x1B[>1u");
+
+        assert_eq!(escape(&t), b"\x1B[27u");
+
+        reply(&mut t, b"\x1B[<u");
+
+        assert_eq!(escape(&t), b"\x1B");
+    }
+
+    #[test]
+    fn blink_phase() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert!(t.blink_visible());
+        assert!(!t.reset_blink());
+
+        t.toggle_blink();
+
+        assert!(!t.blink_visible());
+        assert!(t.reset_blink());
+        assert!(t.blink_visible());
+
+        t.toggle_blink();
+        t.toggle_blink();
+
+        assert!(t.blink_visible());
+        assert!(!t.reset_blink());
+    }
+
+    #[test]
+    fn cursor_blinks_when_focused_and_shown() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert!(t.has_blinking());
+
+        t.set_focus(false, Vec::new()).unwrap();
+
+        assert!(!t.has_blinking());
+
+        t.set_focus(true, Vec::new()).unwrap();
+        reply(&mut t, b"\x1B[?25l");
+
+        assert!(!t.has_blinking());
+
+        reply(&mut t, b"\x1B[?25h\x1B[2 q");
+
+        assert!(!t.has_blinking());
+
+        reply(&mut t, b"\x1B[1 q");
+
+        assert!(t.has_blinking());
+    }
+
+    #[test]
+    fn blinking_text() {
+        let mut t = Term::new_with_size(80, 24);
+
+        // Steady cursor
+        reply(&mut t, b"\x1B[2 q\x1B[5m");
+
+        // Only cells with content blink
+        assert!(!t.has_blinking());
+
+        reply(&mut t, b"x");
+
+        assert!(t.has_blinking());
+
+        // Text keeps blinking without focus
+        t.set_focus(false, Vec::new()).unwrap();
+
+        assert!(t.has_blinking());
+
+        reply(&mut t, b"\x1B[H\x1B[25my");
+
+        assert!(!t.has_blinking());
+    }
+
+    fn paste(t: &Term, text: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        t.encode_paste(text, &mut out).unwrap();
+
+        out
+    }
+
+    #[test]
+    fn paste_without_brackets() {
+        let t = Term::new_with_size(80, 24);
+
+        assert_eq!(paste(&t, "echo a\necho b\r\n"), b"echo a\recho b\r");
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        let mut t = Term::new_with_size(80, 24);
+
+        reply(&mut t, b"\x1B[?2004h");
+
+        assert_eq!(paste(&t, "ls\n"), b"\x1B[200~ls\r\x1B[201~");
+    }
+
+    #[test]
+    fn paste_cannot_end_brackets() {
+        let mut t = Term::new_with_size(80, 24);
+
+        reply(&mut t, b"\x1B[?2004h");
+
+        assert_eq!(paste(&t, "a\x1B[201~rm -rf ~\n"), b"\x1B[200~a[201~rm -rf ~\r\x1B[201~");
+    }
+
+    #[test]
+    fn paste_strips_control_characters() {
+        let t = Term::new_with_size(80, 24);
+
+        // C0 except tab and newlines, DEL and C1 (CSI, ST), other text is kept
+        assert_eq!(String::from_utf8(paste(&t, "a\x00\x03\tb\x7F\u{9B}2J\u{9C}\u{A0}ö")).unwrap(), "a\tb2J\u{A0}ö");
+    }
+
+    #[test]
+    fn synchronized_update() {
+        let mut t = Term::new_with_size(80, 24);
+
+        assert!(!t.synchronized_update());
+        assert_eq!(reply(&mut t, b"\x1B[?2026$p"), b"\x1B[?2026;2$y");
+
+        reply(&mut t, b"\x1B[?2026h");
+
+        assert!(t.synchronized_update());
+        assert_eq!(reply(&mut t, b"\x1B[?2026$p"), b"\x1B[?2026;1$y");
+
+        reply(&mut t, b"\x1B[?2026l");
+
+        assert!(!t.synchronized_update());
+        assert_eq!(reply(&mut t, b"\x1B[?2026$p"), b"\x1B[?2026;2$y");
+    }
+}
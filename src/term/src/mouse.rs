@@ -0,0 +1,333 @@
+//! Encoding of mouse events into the reports sent to the application.
+
+use std::char;
+use std::io;
+use std::io::Write;
+
+use input::{self, Modifiers};
+
+/// Which mouse events are reported.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Tracking {
+    Off,
+    /// X10 compatibility mode (9), only button presses are reported.
+    X10,
+    /// Normal tracking (1000), button presses and releases are reported.
+    Normal,
+    /// Button-event tracking (1002), also reports motion while a button is held.
+    ButtonEvent,
+    /// Any-event tracking (1003), reports all motion.
+    AnyEvent,
+}
+
+impl Default for Tracking {
+    #[inline]
+    fn default() -> Self {
+        Tracking::Off
+    }
+}
+
+/// How mouse reports are encoded.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Encoding {
+    /// ``CSI M Cb Cx Cy`` with each value offset by 32 in a single byte.
+    Default,
+    /// Like `Default` but values are UTF-8 encoded (1005).
+    Utf8,
+    /// ``CSI < Cb ; Cx ; Cy M`` or ``m`` for release (1006).
+    Sgr,
+    /// ``CSI Cb ; Cx ; Cy M`` with decimal values, Cb offset by 32 (1015).
+    Urxvt,
+    /// Like `Sgr` but with the position in pixels (1016).
+    SgrPixels,
+}
+
+impl Default for Encoding {
+    #[inline]
+    fn default() -> Self {
+        Encoding::Default
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+impl Button {
+    /// Button number as used in the reports.
+    fn code(&self) -> u32 {
+        match *self {
+            Button::Left      => 0,
+            Button::Middle    => 1,
+            Button::Right     => 2,
+            Button::WheelUp   => 64,
+            Button::WheelDown => 65,
+        }
+    }
+
+    #[inline]
+    fn is_wheel(&self) -> bool {
+        *self == Button::WheelUp || *self == Button::WheelDown
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Action {
+    Press(Button),
+    Release(Button),
+    Move,
+}
+
+/// A mouse event with the position of the pointer.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Event {
+    pub action:    Action,
+    pub modifiers: Modifiers,
+    /// Cell under the pointer (column, row), 0-indexed.
+    pub cell:      (usize, usize),
+    /// Pointer position in pixels relative to the top left corner of the text area.
+    pub pixel:     (u32, u32),
+}
+
+/// Mouse reporting state of the terminal.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Mouse {
+    pub tracking: Tracking,
+    pub encoding: Encoding,
+    /// Currently held button, used for reporting motion
+    button:       Option<Button>,
+    /// Last reported cell, motion is only reported when the cell changes
+    last_cell:    Option<(usize, usize)>,
+}
+
+impl Mouse {
+    /// Encodes a mouse event according to the tracking mode and encoding, nothing is written if
+    /// the event is not to be reported.
+    pub fn encode<W: Write>(&mut self, event: Event, mut out: W) -> io::Result<()> {
+        let (code, release) = match event.action {
+            Action::Press(b)   => {
+                if !b.is_wheel() {
+                    self.button = Some(b);
+                }
+
+                (b.code(), false)
+            },
+            Action::Release(b) => {
+                if self.button == Some(b) {
+                    self.button = None;
+                }
+
+                // Wheel buttons do not send release events
+                if b.is_wheel() || self.tracking == Tracking::X10 {
+                    return Ok(());
+                }
+
+                (b.code(), true)
+            },
+            Action::Move       => {
+                let report = match self.tracking {
+                    Tracking::ButtonEvent => self.button.is_some(),
+                    Tracking::AnyEvent    => true,
+                    _                     => false,
+                };
+
+                if !report || self.last_cell == Some(event.cell) && self.encoding != Encoding::SgrPixels {
+                    return Ok(());
+                }
+
+                // No button is reported as release
+                (self.button.map(|b| b.code()).unwrap_or(3) + 32, false)
+            },
+        };
+
+        if self.tracking == Tracking::Off {
+            return Ok(());
+        }
+
+        self.last_cell = Some(event.cell);
+
+        let code = if self.tracking == Tracking::X10 {
+            code
+        } else {
+            code + modifier_bits(event.modifiers)
+        };
+        // Reports are 1-indexed
+        let (x, y) = (event.cell.0 as u32 + 1, event.cell.1 as u32 + 1);
+
+        // Only the SGR encodings report which button was released
+        let legacy_code = if release { code | 3 } else { code };
+
+        match self.encoding {
+            Encoding::Default   => {
+                // Coordinates which do not fit are not reported
+                if x > 223 || y > 223 {
+                    return Ok(());
+                }
+
+                try!(out.write_all(b"\x1B[M"));
+
+                out.write_all(&[(legacy_code + 32) as u8, (x + 32) as u8, (y + 32) as u8])
+            },
+            Encoding::Utf8      => {
+                let values = (char::from_u32(legacy_code + 32), char::from_u32(x + 32), char::from_u32(y + 32));
+
+                match values {
+                    (Some(c), Some(x), Some(y)) => write!(out, "\x1B[M{}{}{}", c, x, y),
+                    _                           => Ok(()),
+                }
+            },
+            Encoding::Sgr       => write!(out, "\x1B[<{};{};{}{}", code, x, y, if release { 'm' } else { 'M' }),
+            Encoding::SgrPixels => write!(out, "\x1B[<{};{};{}{}", code, event.pixel.0 + 1, event.pixel.1 + 1, if release { 'm' } else { 'M' }),
+            Encoding::Urxvt     => write!(out, "\x1B[{};{};{}M", legacy_code + 32, x, y),
+        }
+    }
+
+    /// Sets the tracking mode, motion is reported again from the next event.
+    pub fn set_tracking(&mut self, tracking: Tracking) {
+        self.tracking  = tracking;
+        self.last_cell = None;
+    }
+}
+
+/// Returns the modifier bits added to the button code.
+fn modifier_bits(mods: Modifiers) -> u32 {
+    let mut bits = 0;
+
+    if mods.contains(input::SHIFT) { bits |= 4 }
+    if mods.contains(input::ALT)   { bits |= 8 }
+    if mods.contains(input::CTRL)  { bits |= 16 }
+
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use input;
+
+    use super::*;
+
+    fn mouse(tracking: Tracking, encoding: Encoding) -> Mouse {
+        Mouse {
+            tracking: tracking,
+            encoding: encoding,
+            ..Mouse::default()
+        }
+    }
+
+    fn event(action: Action, cell: (usize, usize)) -> Event {
+        Event {
+            action:    action,
+            modifiers: input::NONE,
+            cell:      cell,
+            pixel:     (cell.0 as u32 * 10 + 5, cell.1 as u32 * 20 + 5),
+        }
+    }
+
+    fn encode(m: &mut Mouse, e: Event) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        m.encode(e, &mut out).unwrap();
+
+        out
+    }
+
+    #[test]
+    fn off() {
+        let mut m = mouse(Tracking::Off, Encoding::Sgr);
+
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (0, 0))), b"");
+        assert_eq!(encode(&mut m, event(Action::Move, (1, 0))), b"");
+    }
+
+    #[test]
+    fn default_encoding() {
+        let mut m = mouse(Tracking::Normal, Encoding::Default);
+
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (0, 0))), b"\x1B[M !!");
+        assert_eq!(encode(&mut m, event(Action::Release(Button::Left), (2, 3))), b"\x1B[M##$");
+        // Only presses and releases in normal tracking
+        assert_eq!(encode(&mut m, event(Action::Move, (4, 4))), b"");
+    }
+
+    #[test]
+    fn default_encoding_byte_limit() {
+        let mut m = mouse(Tracking::Normal, Encoding::Default);
+
+        // 222 + 1 + 32 = 255 is the largest value fitting in a byte
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (222, 0))), b"\x1B[M \xFF!");
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (223, 0))), b"");
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (0, 223))), b"");
+    }
+
+    #[test]
+    fn x10() {
+        let mut m = mouse(Tracking::X10, Encoding::Default);
+        let mut e = event(Action::Press(Button::Right), (0, 0));
+
+        // No modifiers and no releases
+        e.modifiers = input::CTRL;
+
+        assert_eq!(encode(&mut m, e), b"\x1B[M\"!!");
+        assert_eq!(encode(&mut m, event(Action::Release(Button::Right), (0, 0))), b"");
+    }
+
+    #[test]
+    fn utf8() {
+        let mut m = mouse(Tracking::Normal, Encoding::Utf8);
+
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (0, 0))), b"\x1B[M !!");
+        // 300 + 1 + 32 = 333 = U+014D
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (300, 0))), "\x1B[M \u{14D}!".as_bytes());
+    }
+
+    #[test]
+    fn sgr() {
+        let mut m = mouse(Tracking::Normal, Encoding::Sgr);
+        let mut e = event(Action::Press(Button::Middle), (299, 9));
+
+        e.modifiers = input::SHIFT | input::CTRL;
+
+        assert_eq!(encode(&mut m, e), b"\x1B[<21;300;10M");
+        assert_eq!(encode(&mut m, event(Action::Release(Button::Middle), (299, 9))), b"\x1B[<1;300;10m");
+        assert_eq!(encode(&mut m, event(Action::Press(Button::WheelUp), (0, 0))), b"\x1B[<64;1;1M");
+        assert_eq!(encode(&mut m, event(Action::Release(Button::WheelUp), (0, 0))), b"");
+    }
+
+    #[test]
+    fn urxvt() {
+        let mut m = mouse(Tracking::Normal, Encoding::Urxvt);
+
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (299, 9))), b"\x1B[32;300;10M");
+        assert_eq!(encode(&mut m, event(Action::Release(Button::Left), (299, 9))), b"\x1B[35;300;10M");
+    }
+
+    #[test]
+    fn sgr_pixels() {
+        let mut m = mouse(Tracking::AnyEvent, Encoding::SgrPixels);
+
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (1, 2))), b"\x1B[<0;16;46M");
+        // Motion within the same cell is reported
+        assert_eq!(encode(&mut m, event(Action::Move, (1, 2))), b"\x1B[<32;16;46M");
+    }
+
+    #[test]
+    fn motion() {
+        let mut m = mouse(Tracking::ButtonEvent, Encoding::Sgr);
+
+        assert_eq!(encode(&mut m, event(Action::Move, (1, 1))), b"");
+        assert_eq!(encode(&mut m, event(Action::Press(Button::Left), (1, 1))), b"\x1B[<0;2;2M");
+        // Same cell
+        assert_eq!(encode(&mut m, event(Action::Move, (1, 1))), b"");
+        assert_eq!(encode(&mut m, event(Action::Move, (2, 1))), b"\x1B[<32;3;2M");
+
+        m.set_tracking(Tracking::AnyEvent);
+
+        assert_eq!(encode(&mut m, event(Action::Release(Button::Left), (2, 1))), b"\x1B[<0;3;2m");
+        assert_eq!(encode(&mut m, event(Action::Move, (3, 1))), b"\x1B[<35;4;2M");
+    }
+}
@@ -0,0 +1,96 @@
+//! Built-in terminfo capabilities reported through XTGETTCAP.
+//!
+//! This describes the capabilities actually supported by `Term`, as a subset of
+//! ``xterm-256color`` which is what we advertise in ``TERM``.
+
+/// Name reported for the ``TN`` (terminal name) capability.
+pub const TERMINAL_NAME: &'static str = "xterm-256color";
+
+/// Capabilities as (name, value), a value of `None` means a boolean capability.
+///
+/// Both termcap and terminfo names are listed since applications query both.
+const CAPABILITIES: &'static [(&'static str, Option<&'static str>)] = &[
+    ("TN",      Some(TERMINAL_NAME)),
+    ("name",    Some(TERMINAL_NAME)),
+    ("Co",      Some("256")),
+    ("colors",  Some("256")),
+    ("RGB",     Some("8")),
+    ("am",      None),
+    ("xenl",    None),
+    ("bel",     Some("\x07")),
+    ("cr",      Some("\r")),
+    ("ind",     Some("\n")),
+    ("ri",      Some("\x1BM")),
+    ("nel",     Some("\x1BE")),
+    ("cup",     Some("\x1B[%i%p1%d;%p2%dH")),
+    ("cuu1",    Some("\x1B[A")),
+    ("cud1",    Some("\n")),
+    ("cuf1",    Some("\x1B[C")),
+    ("cub1",    Some("\x08")),
+    ("cuu",     Some("\x1B[%p1%dA")),
+    ("cud",     Some("\x1B[%p1%dB")),
+    ("cuf",     Some("\x1B[%p1%dC")),
+    ("cub",     Some("\x1B[%p1%dD")),
+    ("hpa",     Some("\x1B[%i%p1%dG")),
+    ("vpa",     Some("\x1B[%i%p1%dd")),
+    ("csr",     Some("\x1B[%i%p1%d;%p2%dr")),
+    ("clear",   Some("\x1B[H\x1B[2J")),
+    ("ed",      Some("\x1B[J")),
+    ("el",      Some("\x1B[K")),
+    ("il",      Some("\x1B[%p1%dL")),
+    ("il1",     Some("\x1B[L")),
+    ("civis",   Some("\x1B[?25l")),
+    ("cnorm",   Some("\x1B[?12l\x1B[?25h")),
+    ("cvvis",   Some("\x1B[?12;25h")),
+    ("Ss",      Some("\x1B[%p1%d q")),
+    ("Se",      Some("\x1B[2 q")),
+    ("sgr0",    Some("\x1B[m")),
+    ("bold",    Some("\x1B[1m")),
+    ("sitm",    Some("\x1B[3m")),
+    ("ritm",    Some("\x1B[23m")),
+    ("smul",    Some("\x1B[4m")),
+    ("rmul",    Some("\x1B[24m")),
+    ("rev",     Some("\x1B[7m")),
+    ("smso",    Some("\x1B[7m")),
+    ("rmso",    Some("\x1B[27m")),
+    ("setaf",   Some("\x1B[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m")),
+    ("setab",   Some("\x1B[%?%p1%{8}%<%t4%p1%d%e%p1%{16}%<%t10%p1%{8}%-%d%e48;5;%p1%d%;m")),
+    ("setrgbf", Some("\x1B[38;2;%p1%d;%p2%d;%p3%dm")),
+    ("setrgbb", Some("\x1B[48;2;%p1%d;%p2%d;%p3%dm")),
+    ("u7",      Some("\x1B[6n")),
+    ("u6",      Some("\x1B[%i%d;%dR")),
+    ("smkx",    Some("\x1B[?1h\x1B=")),
+    ("rmkx",    Some("\x1B[?1l\x1B>")),
+    ("kbs",     Some("\x7F")),
+    ("kcuu1",   Some("\x1BOA")),
+    ("kcud1",   Some("\x1BOB")),
+    ("kcuf1",   Some("\x1BOC")),
+    ("kcub1",   Some("\x1BOD")),
+    ("khome",   Some("\x1BOH")),
+    ("kend",    Some("\x1BOF")),
+    ("kich1",   Some("\x1B[2~")),
+    ("kdch1",   Some("\x1B[3~")),
+    ("kpp",     Some("\x1B[5~")),
+    ("knp",     Some("\x1B[6~")),
+    ("kcbt",    Some("\x1B[Z")),
+    ("kf1",     Some("\x1BOP")),
+    ("kf2",     Some("\x1BOQ")),
+    ("kf3",     Some("\x1BOR")),
+    ("kf4",     Some("\x1BOS")),
+    ("kf5",     Some("\x1B[15~")),
+    ("kf6",     Some("\x1B[17~")),
+    ("kf7",     Some("\x1B[18~")),
+    ("kf8",     Some("\x1B[19~")),
+    ("kf9",     Some("\x1B[20~")),
+    ("kf10",    Some("\x1B[21~")),
+    ("kf11",    Some("\x1B[23~")),
+    ("kf12",    Some("\x1B[24~")),
+];
+
+/// Looks up a capability by its termcap or terminfo name.
+///
+/// Returns `None` if the capability is unknown, `Some(None)` if it is a boolean capability and
+/// `Some(Some(value))` for string and numeric capabilities.
+pub fn lookup(name: &str) -> Option<Option<&'static str>> {
+    CAPABILITIES.iter().find(|&&(n, _)| n == name).map(|&(_, v)| v)
+}
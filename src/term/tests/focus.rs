@@ -0,0 +1,202 @@
+//! Focus reporting through a pseudoterminal.
+//!
+//! The tests act as the application on the slave side while `Term` is driven from the master side
+//! the same way as in `cu2o_loop`.
+
+extern crate chomp;
+extern crate libc;
+extern crate cu2o_term;
+extern crate cu2o_system;
+
+use std::io::{ErrorKind, Read, Write};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+
+use chomp::buffer::{SliceStream, Stream};
+
+use cu2o_term::{ctrl, Term};
+use cu2o_system::Pty;
+
+/// Milliseconds to wait for data which should arrive.
+const TIMEOUT: libc::c_int = 5000;
+/// Milliseconds to wait for data which should not arrive.
+const QUIET: libc::c_int = 20;
+
+struct FakePty {
+    term:   Term,
+    master: Pty,
+    slave:  Pty,
+}
+
+impl FakePty {
+    fn new() -> Self {
+        let (master, mut slave) = Pty::new().unwrap();
+
+        // Pass data through unchanged
+        unsafe {
+            let mut t: libc::termios = mem::zeroed();
+
+            assert_eq!(libc::tcgetattr(slave.as_raw_fd(), &mut t), 0);
+
+            libc::cfmakeraw(&mut t);
+
+            assert_eq!(libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &t), 0);
+        }
+
+        slave.set_nonblocking(true).unwrap();
+
+        FakePty {
+            term:   Term::new_with_size(80, 24),
+            master: master,
+            slave:  slave,
+        }
+    }
+
+    /// Writes `data` from the application and lets the terminal handle it, sending any responses
+    /// back to the application.
+    fn app_write(&mut self, data: &[u8]) {
+        self.slave.write_all(data).unwrap();
+
+        let mut buf = vec![0; data.len()];
+
+        self.master.read_exact(&mut buf).unwrap();
+
+        let mut out    = Vec::new();
+        let mut stream = SliceStream::new(&buf);
+
+        while let Ok(s) = stream.parse(ctrl::parser) {
+            self.term.handle(s, &mut out).unwrap();
+        }
+
+        self.master.write_all(&out).unwrap();
+    }
+
+    /// Changes the focus of the terminal like `Message::Focus`.
+    fn focus(&mut self, focused: bool) {
+        let mut out = Vec::new();
+
+        self.term.set_focus(focused, &mut out).unwrap();
+
+        self.master.write_all(&out).unwrap();
+    }
+
+    /// Waits up to `ms` milliseconds for data to be readable by the application.
+    fn app_readable(&self, ms: libc::c_int) -> bool {
+        let mut fd = libc::pollfd {
+            fd:      self.slave.as_raw_fd(),
+            events:  libc::POLLIN,
+            revents: 0,
+        };
+
+        unsafe { libc::poll(&mut fd, 1, ms) > 0 }
+    }
+
+    /// Reads what the terminal has sent to the application and checks that it is `expected`.
+    ///
+    /// Data passes through the line discipline asynchronously, so this waits until the expected
+    /// amount has arrived and then briefly for anything unexpected.
+    fn app_expect(&mut self, expected: &[u8]) {
+        let mut data = Vec::new();
+        let mut buf  = [0; 64];
+
+        while data.len() < expected.len() && self.app_readable(TIMEOUT) || self.app_readable(QUIET) {
+            match self.slave.read(&mut buf) {
+                Ok(n)  => data.extend_from_slice(&buf[..n]),
+                Err(e) => assert_eq!(e.kind(), ErrorKind::WouldBlock),
+            }
+        }
+
+        assert_eq!(data, expected);
+    }
+}
+
+#[test]
+fn no_events_when_disabled() {
+    let mut p = FakePty::new();
+
+    p.focus(false);
+    p.focus(true);
+
+    p.app_expect(b"");
+}
+
+#[test]
+fn enabling_reports_current_focus() {
+    let mut p = FakePty::new();
+
+    p.app_write(b"\x1B[?1004h");
+
+    p.app_expect(b"\x1B[I");
+}
+
+#[test]
+fn focus_change_before_enabling_is_remembered() {
+    let mut p = FakePty::new();
+
+    p.focus(false);
+
+    p.app_expect(b"");
+
+    p.app_write(b"\x1B[?1004h");
+
+    p.app_expect(b"\x1B[O");
+}
+
+#[test]
+fn enabling_twice_reports_once() {
+    let mut p = FakePty::new();
+
+    p.app_write(b"\x1B[?1004h");
+    p.app_write(b"\x1B[?1004h");
+
+    p.app_expect(b"\x1B[I");
+}
+
+#[test]
+fn focus_changes_are_reported() {
+    let mut p = FakePty::new();
+
+    p.app_write(b"\x1B[?1004h");
+
+    p.app_expect(b"\x1B[I");
+
+    p.focus(false);
+
+    p.app_expect(b"\x1B[O");
+
+    p.focus(true);
+
+    p.app_expect(b"\x1B[I");
+
+    // No change
+    p.focus(true);
+
+    p.app_expect(b"");
+}
+
+#[test]
+fn disabling_stops_reports() {
+    let mut p = FakePty::new();
+
+    p.app_write(b"\x1B[?1004h");
+
+    p.app_expect(b"\x1B[I");
+
+    p.app_write(b"\x1B[?1004l");
+    p.focus(false);
+
+    p.app_expect(b"");
+}
+
+#[test]
+fn mode_is_reported() {
+    let mut p = FakePty::new();
+
+    p.app_write(b"\x1B[?1004$p");
+
+    p.app_expect(b"\x1B[?1004;2$y");
+
+    p.app_write(b"\x1B[?1004h\x1B[?1004$p");
+
+    p.app_expect(b"\x1B[I\x1B[?1004;1$y");
+}
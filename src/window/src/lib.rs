@@ -1,3 +1,4 @@
+extern crate clipboard;
 extern crate glutin;
 extern crate glium;
 #[macro_use]
@@ -5,11 +6,14 @@ extern crate log;
 extern crate mio;
 extern crate time;
 extern crate freetype;
+#[cfg(all(unix, not(target_os = "macos")))]
+extern crate x11;
 
 extern crate cu2o_term;
 extern crate cu2o_gl;
 extern crate cu2o_loop;
 
+mod urgency;
 mod window;
 
-pub use window::{Action, Error, Font, FontFaces, Window, WindowProxy};
+pub use window::{Error, Font, FontFaces, ProxyNotifier, Window, WindowProxy};
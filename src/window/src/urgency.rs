@@ -0,0 +1,42 @@
+//! Urgency hint used to draw attention to the window, eg. when the bell rings while unfocused.
+
+use glutin::Window;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn set_urgent(window: &Window, urgent: bool) {
+    use glutin::os::unix::WindowExt;
+    use x11::xlib;
+
+    let (display, win) = match (window.get_xlib_display(), window.get_xlib_window()) {
+        (Some(d), Some(w)) => (d as *mut xlib::Display, w as xlib::Window),
+        // Not running on X11
+        _                  => return,
+    };
+
+    unsafe {
+        let mut hints = xlib::XGetWMHints(display, win);
+
+        if hints.is_null() {
+            hints = xlib::XAllocWMHints();
+
+            if hints.is_null() {
+                return;
+            }
+        }
+
+        if urgent {
+            (*hints).flags |= xlib::XUrgencyHint;
+        } else {
+            (*hints).flags &= !xlib::XUrgencyHint;
+        }
+
+        xlib::XSetWMHints(display, win, hints);
+        xlib::XFree(hints as *mut _);
+        xlib::XFlush(display);
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn set_urgent(_window: &Window, _urgent: bool) {
+    // Not supported
+}
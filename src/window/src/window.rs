@@ -1,27 +1,41 @@
+use std::cmp;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
 
+use clipboard::ClipboardContext;
 use cu2o_gl::glyph::Error as GlyphError;
 use cu2o_gl::glyph::{FreeType, FreeTypeConfig, Map, MapError, Renderer};
 use cu2o_gl::{GlTerm, FontStyle};
 use cu2o_gl::color::Manager;
-use cu2o_loop::Message;
+use cu2o_loop::{Event as TermEvent, Message, Notifier};
 use cu2o_term::Term;
+use cu2o_term::input::{self, Key, KeyState, Modifiers};
+use cu2o_term::mouse;
 use freetype::Error as FtError;
 use freetype::Library as FtLibrary;
 use glium::backend::Facade;
 use glium::{Display, DisplayBuild};
 use glutin::Api::OpenGl;
-use glutin::{Event, GlRequest, WindowBuilder};
+use glutin::{ElementState, Event, GlRequest, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowBuilder};
 use mio::Sender;
 use time::{Duration, PreciseTime};
 
+use urgency;
+
 pub use glutin::WindowProxy;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum Action {
-    Quit,
+/// Wakes up the window event loop from the terminal event loop.
+pub struct ProxyNotifier(WindowProxy);
+
+impl Notifier for ProxyNotifier {
+    fn notify(&self) {
+        self.0.wakeup_event_loop();
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -93,19 +107,329 @@ impl<'a> FontFaces<'a> {
     }
 }
 
+/// Tracks the state of the modifier keys, left and right keys are tracked separately.
+#[derive(Clone, Copy, Debug, Default)]
+struct ModifierState {
+    shift: (bool, bool),
+    ctrl:  (bool, bool),
+    alt:   (bool, bool),
+    logo:  (bool, bool),
+}
+
+impl ModifierState {
+    /// Updates the state from a key event, keys which are not modifier keys are ignored.
+    fn update(&mut self, key: VirtualKeyCode, pressed: bool) {
+        use glutin::VirtualKeyCode::*;
+
+        match key {
+            LShift   => self.shift.0 = pressed,
+            RShift   => self.shift.1 = pressed,
+            LControl => self.ctrl.0  = pressed,
+            RControl => self.ctrl.1  = pressed,
+            LMenu    => self.alt.0   = pressed,
+            RMenu    => self.alt.1   = pressed,
+            LWin     => self.logo.0  = pressed,
+            RWin     => self.logo.1  = pressed,
+            _        => {},
+        }
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        let mut m = Modifiers::empty();
+
+        if self.shift.0 || self.shift.1 { m.insert(input::SHIFT) }
+        if self.ctrl.0  || self.ctrl.1  { m.insert(input::CTRL) }
+        if self.alt.0   || self.alt.1   { m.insert(input::ALT) }
+        if self.logo.0  || self.logo.1  { m.insert(input::SUPER) }
+
+        m
+    }
+}
+
+/// Maps keys which do not produce printable text, keypad keys and modifier keys to terminal keys.
+fn named_key(key: VirtualKeyCode) -> Option<Key> {
+    use glutin::VirtualKeyCode::*;
+
+    Some(match key {
+        Return   => Key::Enter,
+        Tab      => Key::Tab,
+        Back     => Key::Backspace,
+        Escape   => Key::Escape,
+        Up       => Key::Up,
+        Down     => Key::Down,
+        Left     => Key::Left,
+        Right    => Key::Right,
+        Home     => Key::Home,
+        End      => Key::End,
+        PageUp   => Key::PageUp,
+        PageDown => Key::PageDown,
+        Insert   => Key::Insert,
+        Delete   => Key::Delete,
+        F1       => Key::F(1),
+        F2       => Key::F(2),
+        F3       => Key::F(3),
+        F4       => Key::F(4),
+        F5       => Key::F(5),
+        F6       => Key::F(6),
+        F7       => Key::F(7),
+        F8       => Key::F(8),
+        F9       => Key::F(9),
+        F10      => Key::F(10),
+        F11      => Key::F(11),
+        F12      => Key::F(12),
+        Numpad0  => Key::Keypad('0'),
+        Numpad1  => Key::Keypad('1'),
+        Numpad2  => Key::Keypad('2'),
+        Numpad3  => Key::Keypad('3'),
+        Numpad4  => Key::Keypad('4'),
+        Numpad5  => Key::Keypad('5'),
+        Numpad6  => Key::Keypad('6'),
+        Numpad7  => Key::Keypad('7'),
+        Numpad8  => Key::Keypad('8'),
+        Numpad9  => Key::Keypad('9'),
+        Add      => Key::Keypad('+'),
+        Subtract => Key::Keypad('-'),
+        Multiply => Key::Keypad('*'),
+        Divide   => Key::Keypad('/'),
+        Decimal  => Key::Keypad('.'),
+        NumpadComma  => Key::Keypad(','),
+        NumpadEquals => Key::Keypad('='),
+        NumpadEnter  => Key::KeypadEnter,
+        LShift   => Key::LeftShift,
+        RShift   => Key::RightShift,
+        LControl => Key::LeftCtrl,
+        RControl => Key::RightCtrl,
+        LMenu    => Key::LeftAlt,
+        RMenu    => Key::RightAlt,
+        LWin     => Key::LeftSuper,
+        RWin     => Key::RightSuper,
+        _        => return None,
+    })
+}
+
+/// Maps keys to the character they produce on a US layout, used for Ctrl chords since the text
+/// received for these is already converted to control characters, and for releases which do not
+/// receive any text.
+fn chord_char(key: VirtualKeyCode, shift: bool) -> Option<char> {
+    use glutin::VirtualKeyCode::*;
+
+    let (c, shifted) = match key {
+        A => ('a', 'A'), B => ('b', 'B'), C => ('c', 'C'), D => ('d', 'D'), E => ('e', 'E'),
+        F => ('f', 'F'), G => ('g', 'G'), H => ('h', 'H'), I => ('i', 'I'), J => ('j', 'J'),
+        K => ('k', 'K'), L => ('l', 'L'), M => ('m', 'M'), N => ('n', 'N'), O => ('o', 'O'),
+        P => ('p', 'P'), Q => ('q', 'Q'), R => ('r', 'R'), S => ('s', 'S'), T => ('t', 'T'),
+        U => ('u', 'U'), V => ('v', 'V'), W => ('w', 'W'), X => ('x', 'X'), Y => ('y', 'Y'),
+        Z => ('z', 'Z'),
+        Key1 => ('1', '!'), Key2 => ('2', '@'), Key3 => ('3', '#'), Key4 => ('4', '$'),
+        Key5 => ('5', '%'), Key6 => ('6', '^'), Key7 => ('7', '&'), Key8 => ('8', '*'),
+        Key9 => ('9', '('), Key0 => ('0', ')'),
+        Space      => (' ', ' '),
+        Minus      => ('-', '_'),
+        Equals     => ('=', '+'),
+        LBracket   => ('[', '{'),
+        RBracket   => (']', '}'),
+        Backslash  => ('\\', '|'),
+        Semicolon  => (';', ':'),
+        Apostrophe => ('\'', '"'),
+        Grave      => ('`', '~'),
+        Comma      => (',', '<'),
+        Period     => ('.', '>'),
+        Slash      => ('/', '?'),
+        _          => return None,
+    };
+
+    Some(if shift { shifted } else { c })
+}
+
+/// Returns true if the key combination pastes from the clipboard, Shift + Insert or
+/// Ctrl + Shift + V.
+fn is_paste(key: VirtualKeyCode, mods: Modifiers) -> bool {
+    (key == VirtualKeyCode::Insert && mods == input::SHIFT)
+        || (key == VirtualKeyCode::V && mods == input::CTRL | input::SHIFT)
+}
+
+/// Action resulting from a keyboard event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum KeyAction {
+    /// Sends the key to the terminal
+    Key(Key, Modifiers, KeyState),
+    /// Pastes the clipboard
+    Paste,
+}
+
+/// Turns glutin key and character events into terminal keys.
+///
+/// Keys producing text are reported as a key event followed by a character event, unless the key
+/// maps to a terminal key directly it is sent when the character is received.
+#[derive(Debug)]
+struct KeyInput {
+    mods:       ModifierState,
+    /// Keys currently held down, used to detect repeats
+    held:       Vec<VirtualKeyCode>,
+    /// Set when the text of the next received character has already been sent as a key
+    skip_char:  bool,
+    /// Character the last pressed key produces on a US layout, see `received`
+    char_key:   Option<char>,
+    /// State of the last key press or repeat producing text, applied to the received character
+    char_state: KeyState,
+}
+
+impl KeyInput {
+    fn new() -> Self {
+        KeyInput {
+            mods:       ModifierState::default(),
+            held:       Vec::new(),
+            skip_char:  false,
+            char_key:   None,
+            char_state: KeyState::Press,
+        }
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        self.mods.modifiers()
+    }
+
+    /// Handles a key event.
+    fn key(&mut self, vk: VirtualKeyCode, pressed: bool) -> Option<KeyAction> {
+        let state = if !pressed {
+            self.held.retain(|&k| k != vk);
+
+            KeyState::Release
+        } else if self.held.contains(&vk) {
+            KeyState::Repeat
+        } else {
+            self.held.push(vk);
+
+            KeyState::Press
+        };
+
+        self.mods.update(vk, pressed);
+
+        let m = self.mods.modifiers();
+
+        if is_paste(vk, m) {
+            return if pressed { Some(KeyAction::Paste) } else { None };
+        }
+
+        if pressed {
+            self.skip_char = false;
+            self.char_key  = chord_char(vk, m.contains(input::SHIFT));
+        }
+
+        let key = named_key(vk).or_else(||
+            // Ctrl chords are resolved from the key since the received text is a control character
+            if m.contains(input::CTRL) || state == KeyState::Release {
+                chord_char(vk, m.contains(input::SHIFT)).map(Key::Char)
+            } else {
+                None
+            });
+
+        match key {
+            Some(key) => {
+                match key {
+                    // The text has already been sent with the key
+                    Key::Keypad(_) | Key::Char(_) => self.skip_char = pressed,
+                    _                             => {},
+                }
+
+                Some(KeyAction::Key(key, m, state))
+            },
+            // Text is sent when the character is received, releases of such keys (eg. Shift)
+            // must not affect it
+            None      => {
+                if pressed {
+                    self.char_state = state;
+                }
+
+                None
+            },
+        }
+    }
+
+    /// Handles a received character.
+    fn received(&mut self, c: char) -> Option<KeyAction> {
+        if self.skip_char {
+            self.skip_char = false;
+
+            return None;
+        }
+
+        // Control characters are handled through `key`
+        if (c as u32) < 0x20 || c == '\x7F' {
+            return None;
+        }
+
+        let mut m = self.mods.modifiers();
+
+        // Alt has been consumed by the layout if the key produces a different character than on
+        // a US layout, eg. AltGr reported as Alt
+        if m.contains(input::ALT) && self.char_key.map_or(false, |k| k != c) {
+            m.remove(input::ALT);
+        }
+
+        let state = self.char_state;
+
+        // Characters without a key event (compose, input methods) are presses
+        self.char_state = KeyState::Press;
+
+        Some(KeyAction::Key(Key::Char(c), m, state))
+    }
+}
+
+/// Reads the text from the clipboard.
+fn clipboard_text() -> Option<String> {
+    match ClipboardContext::new().and_then(|c| c.get_contents()) {
+        Ok(text) => Some(text),
+        Err(e)   => {
+            error!("Failed to read clipboard: {}", e);
+
+            None
+        },
+    }
+}
+
+/// Creates a mouse event for the pointer at `pos` in framebuffer pixels, converting it to the
+/// cell under the pointer. The text area is centered in the framebuffer, see `Window::run`.
+fn mouse_event(action: mouse::Action, mods: Modifiers, pos: (i32, i32), cell: (u32, u32), bufsize: (u32, u32)) -> mouse::Event {
+    // Avoid dividing by zero if the font did not report a size
+    let cell   = (cmp::max(cell.0, 1), cmp::max(cell.1, 1));
+    let margin = ((bufsize.0 % cell.0) / 2, (bufsize.1 % cell.1) / 2);
+    let area   = (bufsize.0 / cell.0 * cell.0, bufsize.1 / cell.1 * cell.1);
+    // Clamp to the text area
+    let x      = cmp::min((cmp::max(pos.0, 0) as u32).saturating_sub(margin.0), area.0.saturating_sub(1));
+    let y      = cmp::min((cmp::max(pos.1, 0) as u32).saturating_sub(margin.1), area.1.saturating_sub(1));
+
+    mouse::Event {
+        action:    action,
+        modifiers: mods,
+        cell:      ((x / cell.0) as usize, (y / cell.1) as usize),
+        pixel:     (x, y),
+    }
+}
+
+/// Minimum time between runs of the bell command if the visual bell is disabled.
+const BELL_INTERVAL: u64 = 150;
+
 pub struct Window<C>
   where C: Manager {
     /// Glium window and OpenGl context
-    display: Display,
-    msgs:    Receiver<Action>,
+    display:      Display,
     /// Terminal renderer
-    gl:      GlTerm<C>,
+    gl:           GlTerm<C>,
+    /// Shell command run when the bell rings
+    bell_command: Option<String>,
+    /// Set while the bell command is running
+    bell_running: Arc<AtomicBool>,
+    /// When the bell command was last started
+    bell_last:    Option<Instant>,
+    /// Minimum time between runs of the bell command
+    bell_interval: StdDuration,
 }
 
 impl<C> Window<C>
   where C: Manager {
     // TODO: Result
-    pub fn new(faces: FontFaces, colors: C, msgs: Receiver<Action>) -> Self {
+    pub fn new(faces: FontFaces, colors: C) -> Self {
         info!("creating window");
 
         let display = WindowBuilder::new()
@@ -122,9 +446,65 @@ impl<C> Window<C>
         faces.load_fonts(&mut ft_lib, &mut f_map, scale).unwrap();
 
         Window {
-            display: display,
-            msgs:    msgs,
-            gl:      GlTerm::new(ctx, colors, f_map).unwrap(),
+            display:      display,
+            gl:           GlTerm::new(ctx, colors, f_map).unwrap(),
+            bell_command: None,
+            bell_running: Arc::new(AtomicBool::new(false)),
+            bell_last:    None,
+            bell_interval: StdDuration::from_millis(BELL_INTERVAL),
+        }
+    }
+
+    /// Sets the duration of the visual bell flash, `None` disables the visual bell.
+    pub fn set_visual_bell(&mut self, duration: Option<StdDuration>) {
+        self.gl.set_visual_bell(duration);
+
+        self.bell_interval = duration.unwrap_or(StdDuration::from_millis(BELL_INTERVAL));
+    }
+
+    /// Sets a shell command to run when the bell rings.
+    ///
+    /// The command is not run again while it is still running or within the visual bell
+    /// duration, applications may ring the bell many times in a row.
+    pub fn set_bell_command(&mut self, command: Option<String>) {
+        self.bell_command = command;
+    }
+
+    fn ring_bell(&mut self, focused: bool) {
+        info!("Window: bell");
+
+        self.gl.ring_bell();
+
+        if !focused {
+            self.display.get_window().map(|w| urgency::set_urgent(&w, true));
+        }
+
+        if let Some(ref cmd) = self.bell_command {
+            let recent = self.bell_last.map_or(false, |t| t.elapsed() < self.bell_interval);
+
+            if recent || self.bell_running.swap(true, Ordering::SeqCst) {
+                return;
+            }
+
+            self.bell_last = Some(Instant::now());
+
+            match Command::new("sh").arg("-c").arg(cmd).spawn() {
+                // Reap the child without blocking the window
+                Ok(mut child) => {
+                    let running = self.bell_running.clone();
+
+                    thread::spawn(move || {
+                        let _ = child.wait();
+
+                        running.store(false, Ordering::SeqCst);
+                    });
+                },
+                Err(e)        => {
+                    warn!("Window: failed to run bell command {:?}: {}", cmd, e);
+
+                    self.bell_running.store(false, Ordering::SeqCst);
+                },
+            }
         }
     }
 
@@ -133,7 +513,12 @@ impl<C> Window<C>
         self.display.get_window().unwrap().create_window_proxy()
     }
 
-    pub fn run(&mut self, terminal: Arc<Mutex<Term>>, msg: Sender<Message>) {
+    /// Notifier to pass to `cu2o_loop::Builder::spawn`.
+    pub fn notifier(&self) -> ProxyNotifier {
+        ProxyNotifier(self.create_proxy())
+    }
+
+    pub fn run(&mut self, terminal: Arc<Mutex<Term>>, msg: Sender<Message>, events: Receiver<TermEvent>) {
         unsafe { self.display.get_window().unwrap().make_current().unwrap() };
         self.display.get_window().unwrap().show();
 
@@ -150,6 +535,10 @@ impl<C> Window<C>
         info!("Window: starting event loop");
 
         let mut counter = FpsCounter::new();
+        let mut keys    = KeyInput::new();
+        // Last known pointer position
+        let mut pointer = (0, 0);
+        let mut focused = true;
 
         for i in self.display.wait_events() {
             match i {
@@ -158,20 +547,99 @@ impl<C> Window<C>
 
                     break;
                 },
-                // TODO: Proper keyboard handling
-                Event::ReceivedCharacter(c) => msg.send(Message::Character(c)).unwrap(),
-                Event::Focused(got_focus)   => msg.send(Message::Focus(got_focus)).unwrap(),
-                Event::MouseMoved(_)        => {},
+                Event::KeyboardInput(state, _, Some(vk)) => {
+                    match keys.key(vk, state == ElementState::Pressed) {
+                        Some(KeyAction::Key(key, m, state)) => msg.send(Message::Key { key: key, modifiers: m, state: state }).unwrap(),
+                        Some(KeyAction::Paste)              => if let Some(text) = clipboard_text() {
+                            msg.send(Message::Paste(text)).unwrap();
+                        },
+                        None                                => {},
+                    }
+                },
+                Event::ReceivedCharacter(c) => {
+                    if let Some(KeyAction::Key(key, m, state)) = keys.received(c) {
+                        msg.send(Message::Key { key: key, modifiers: m, state: state }).unwrap();
+                    }
+                },
+                Event::Focused(got_focus)   => {
+                    focused = got_focus;
+
+                    if focused {
+                        self.display.get_window().map(|w| urgency::set_urgent(&w, false));
+                    }
+
+                    msg.send(Message::Focus(got_focus)).unwrap();
+                },
+                Event::MouseMoved(pos)      => {
+                    pointer = pos;
+
+                    let tracking = terminal.lock().expect("term::Term mutex poisoned").mouse_tracking();
+
+                    // Motion is reported far more often than anything else, only send it if the
+                    // application is interested
+                    if tracking == mouse::Tracking::ButtonEvent || tracking == mouse::Tracking::AnyEvent {
+                        msg.send(Message::Mouse(mouse_event(mouse::Action::Move, keys.modifiers(), pointer, cell, bufsize))).unwrap();
+                    }
+                },
+                Event::MouseInput(state, button) => {
+                    let button = match button {
+                        MouseButton::Left   => mouse::Button::Left,
+                        MouseButton::Middle => mouse::Button::Middle,
+                        MouseButton::Right  => mouse::Button::Right,
+                        _                   => continue,
+                    };
+                    let action = match state {
+                        ElementState::Pressed  => mouse::Action::Press(button),
+                        ElementState::Released => mouse::Action::Release(button),
+                    };
+
+                    msg.send(Message::Mouse(mouse_event(action, keys.modifiers(), pointer, cell, bufsize))).unwrap();
+                },
+                Event::MouseWheel(delta, _) => {
+                    let (y, lines) = match delta {
+                        MouseScrollDelta::LineDelta(_, y)  => (y, cmp::max(1, y.abs().round() as usize)),
+                        // Touchpads send many small deltas, report one step each
+                        MouseScrollDelta::PixelDelta(_, y) => (y, 1),
+                    };
+                    let button = if y > 0.0 {
+                        mouse::Button::WheelUp
+                    } else if y < 0.0 {
+                        mouse::Button::WheelDown
+                    } else {
+                        continue;
+                    };
+
+                    for _ in 0..lines {
+                        msg.send(Message::Mouse(mouse_event(mouse::Action::Press(button), keys.modifiers(), pointer, cell, bufsize))).unwrap();
+                    }
+                },
                 Event::Awakened             => {
+                    let mut exited = false;
+
                     // We ignore errors (senders disconnected, channel empty)
-                    match self.msgs.try_recv().ok() {
-                        Some(Action::Quit) => {
-                            info!("Received Action::Quit, exiting window thread");
+                    while let Ok(e) = events.try_recv() {
+                        match e {
+                            TermEvent::Bell                => self.ring_bell(focused),
+                            TermEvent::ChildExited(status) => {
+                                info!("Child exited ({}), exiting window thread", status);
+
+                                exited = true;
+                            },
+                            TermEvent::Quit(signal)        => {
+                                info!("Received {:?}, exiting window thread", signal);
+
+                                exited = true;
+                            },
+                            TermEvent::Closed              => {
+                                info!("Pseudoterminal closed, exiting window thread");
+
+                                exited = true;
+                            },
+                        }
+                    }
 
-                            break;
-                        },
-                        // No event, continue with render nothing
-                        None => {},
+                    if exited {
+                        break;
                     }
 
                     info!("Window: rendering");
@@ -210,6 +678,11 @@ impl<C> Window<C>
                     target.finish().unwrap();
 
                     counter.increment();
+
+                    // Keep rendering until the visual bell has faded
+                    if self.gl.bell_active() {
+                        self.create_proxy().wakeup_event_loop();
+                    }
                 },
                 // TODO: More events
                 _ => {}, // println!("w {:?}", i)
@@ -249,3 +722,97 @@ impl FpsCounter {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use glutin::VirtualKeyCode;
+
+    use cu2o_term::input::{self, Key, KeyState};
+
+    use super::{KeyAction, KeyInput};
+
+    #[test]
+    fn text_is_sent_when_received() {
+        let mut k = KeyInput::new();
+
+        assert_eq!(k.key(VirtualKeyCode::A, true), None);
+        assert_eq!(k.received('a'), Some(KeyAction::Key(Key::Char('a'), input::NONE, KeyState::Press)));
+        assert_eq!(k.key(VirtualKeyCode::A, false), Some(KeyAction::Key(Key::Char('a'), input::NONE, KeyState::Release)));
+    }
+
+    #[test]
+    fn repeats() {
+        let mut k = KeyInput::new();
+
+        k.key(VirtualKeyCode::A, true);
+        k.received('a');
+        k.key(VirtualKeyCode::A, true);
+
+        assert_eq!(k.received('a'), Some(KeyAction::Key(Key::Char('a'), input::NONE, KeyState::Repeat)));
+    }
+
+    #[test]
+    fn release_does_not_affect_unmapped_char() {
+        let mut k = KeyInput::new();
+
+        assert_eq!(k.key(VirtualKeyCode::LShift, true), Some(KeyAction::Key(Key::LeftShift, input::SHIFT, KeyState::Press)));
+        assert_eq!(k.key(VirtualKeyCode::LShift, false), Some(KeyAction::Key(Key::LeftShift, input::NONE, KeyState::Release)));
+        // Key without a terminal key or US layout character
+        assert_eq!(k.key(VirtualKeyCode::Compose, true), None);
+        assert_eq!(k.key(VirtualKeyCode::Compose, false), None);
+        assert_eq!(k.received('ö'), Some(KeyAction::Key(Key::Char('ö'), input::NONE, KeyState::Press)));
+    }
+
+    #[test]
+    fn state_is_reset_after_received_char() {
+        let mut k = KeyInput::new();
+
+        k.key(VirtualKeyCode::A, true);
+        k.key(VirtualKeyCode::A, true);
+
+        assert_eq!(k.received('a'), Some(KeyAction::Key(Key::Char('a'), input::NONE, KeyState::Repeat)));
+        // Eg. from an input method
+        assert_eq!(k.received('é'), Some(KeyAction::Key(Key::Char('é'), input::NONE, KeyState::Press)));
+    }
+
+    #[test]
+    fn ctrl_chords_use_the_key() {
+        let mut k = KeyInput::new();
+
+        k.key(VirtualKeyCode::LControl, true);
+
+        assert_eq!(k.key(VirtualKeyCode::C, true), Some(KeyAction::Key(Key::Char('c'), input::CTRL, KeyState::Press)));
+        assert_eq!(k.received('\x03'), None);
+    }
+
+    #[test]
+    fn alt_chords_use_the_received_char() {
+        let mut k = KeyInput::new();
+
+        k.key(VirtualKeyCode::LMenu, true);
+
+        assert_eq!(k.key(VirtualKeyCode::Q, true), None);
+        assert_eq!(k.received('q'), Some(KeyAction::Key(Key::Char('q'), input::ALT, KeyState::Press)));
+    }
+
+    #[test]
+    fn altgr_reported_as_alt() {
+        let mut k = KeyInput::new();
+
+        // AltGr + Q on a German layout
+        k.key(VirtualKeyCode::RMenu, true);
+        k.key(VirtualKeyCode::Q, true);
+
+        assert_eq!(k.received('@'), Some(KeyAction::Key(Key::Char('@'), input::NONE, KeyState::Press)));
+    }
+
+    #[test]
+    fn paste() {
+        let mut k = KeyInput::new();
+
+        k.key(VirtualKeyCode::LShift, true);
+
+        assert_eq!(k.key(VirtualKeyCode::Insert, true), Some(KeyAction::Paste));
+        assert_eq!(k.key(VirtualKeyCode::Insert, false), None);
+    }
+}